@@ -74,6 +74,14 @@ new_addr_attr!(AlternateServer, Type::ALTERNATE_SERVER);
 new_addr_attr!(XorMappedAddress, Type::XOR_MAPPED_ADDRESS, xor);
 new_addr_attr!(XorPeerAddress, Type::XOR_PEER_ADDRESS, xor);
 new_addr_attr!(XorRelayedAddress, Type::XOR_RELAYED_ADDRESS, xor);
+// https://datatracker.ietf.org/doc/html/rfc5780#section-7
+new_addr_attr!(ResponseOrigin, Type::RESPONSE_ORIGIN);
+new_addr_attr!(OtherAddress, Type::OTHER_ADDRESS);
+// https://datatracker.ietf.org/doc/html/rfc3489#section-11.2
+new_addr_attr!(ResponseAddress, Type::RESPONSE_ADDRESS);
+new_addr_attr!(SourceAddress, Type::SOURCE_ADDRESS);
+new_addr_attr!(ChangedAddress, Type::CHANGED_ADDRESS);
+new_addr_attr!(ReflectedFrom, Type::REFLECTED_FROM);
 
 pub fn encode<'a, X: OptXor>(
     t: Type,
@@ -388,9 +396,4 @@ mod test {
             assert_eq!(&buf[..written_len], expected);
         }
     }
-
-    mod private {
-        crate::new_addr_attr!(TestAttr1, crate::attribute::Type::MAPPED_ADDRESS);
-        crate::new_addr_family_attr!(TestAttr2, crate::attribute::Type::MAPPED_ADDRESS);
-    }
 }
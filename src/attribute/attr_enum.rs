@@ -7,6 +7,10 @@ macro_rules! define_attribute_enum {
             UnknownAttribute($crate::attribute::UnknownAttribute<'a>),
         }
 
+        impl $attr<'_> {
+            pub const TYPES: &'static [$crate::attribute::Type] = &[$($variant::TYPE,)+];
+        }
+
         $(impl <'a> ::core::convert::From<$variant$(<$l>)?> for $attr<'a> {
             #[inline]
             fn from(v: $variant$(<$l>)?) -> Self {
@@ -0,0 +1,75 @@
+use super::{integer, Attribute, DecodeAttribute, EncodeAttribute, StunError, TransactionId, Type};
+use crate::channel_data;
+
+// https://datatracker.ietf.org/doc/html/rfc8656#section-18.1
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ChannelNumber(u16);
+
+impl ChannelNumber {
+    pub const TYPE: Type = Type::CHANNEL_NUMBER;
+
+    #[inline]
+    pub fn new(channel: u16) -> Result<Self, StunError> {
+        channel_data::validate_channel(channel).map(|()| Self(channel))
+    }
+
+    #[inline]
+    pub fn channel(&self) -> u16 {
+        self.0
+    }
+}
+
+impl Attribute for ChannelNumber {
+    #[inline]
+    fn attribute_type(&self) -> Type {
+        Self::TYPE
+    }
+}
+
+impl EncodeAttribute for ChannelNumber {
+    #[inline]
+    fn encoded_value_len(&self) -> u16 {
+        LEN as _
+    }
+
+    #[inline]
+    fn encode<'a>(&self, dst: &'a mut [u8], _: &TransactionId) -> Result<&'a mut [u8], StunError> {
+        integer::encode_integer::<_, LEN, TOTAL_LEN>(Self::TYPE, dst, || {
+            ((self.0 as u32) << 16).to_be_bytes()
+        })
+    }
+}
+
+impl DecodeAttribute<'_> for ChannelNumber {
+    // the RFFU bits are ignored on receipt
+    #[inline]
+    fn decode(_: Type, src: &[u8], _: &TransactionId) -> Result<Self, StunError> {
+        let x = integer::decode_integer(src, u32::from_be_bytes)?;
+        Self::new((x >> 16) as u16)
+    }
+}
+
+const LEN: usize = core::mem::size_of::<u32>();
+const TOTAL_LEN: usize = LEN + super::RawAttribute::TL_LEN;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::error::StunErrorKind;
+
+    #[test]
+    fn test_channel_number() {
+        let (mut buf, id) = ([0; 8], TransactionId::new([0; 12]));
+        let channel = ChannelNumber::new(0x4FFF).unwrap();
+        assert!(channel.encode(&mut buf, &id).unwrap().is_empty());
+        assert_eq!(buf, [0x00, 0x0C, 0x00, 0x04, 0x4F, 0xFF, 0x00, 0x00]);
+        let decoded = ChannelNumber::decode(Type::CHANNEL_NUMBER, &[0x40, 0x00, 0xAB, 0xCD], &id);
+        assert_eq!(decoded.unwrap().channel(), 0x4000);
+        let err =
+            ChannelNumber::decode(Type::CHANNEL_NUMBER, &[0x3F, 0xFF, 0, 0], &id).unwrap_err();
+        assert_eq!(err.error_kind(), StunErrorKind::InvalidParameter);
+        let err = ChannelNumber::decode(Type::CHANNEL_NUMBER, &[0x40, 0x00], &id).unwrap_err();
+        assert_eq!(err.error_kind(), StunErrorKind::InvalidParameter);
+        assert!(ChannelNumber::new(0x5000).is_err());
+    }
+}
@@ -0,0 +1,61 @@
+use super::{Attribute, DecodeAttribute, EncodeAttribute, StunError, TransactionId, Type};
+use crate::error::new_error;
+
+// https://datatracker.ietf.org/doc/html/rfc3489#section-11.2.7
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Password<T>(T);
+
+impl<T> Password<T> {
+    pub const TYPE: Type = Type::PASSWORD;
+
+    #[inline]
+    pub fn new(password: T) -> Result<Self, StunError>
+    where
+        T: AsRef<[u8]>,
+    {
+        let len = password.as_ref().len();
+        if len % 4 == 0 {
+            return Ok(Self(password));
+        }
+        new_error!(
+            InvalidLen { len: usize },
+            InvalidParameter,
+            "PASSWORD must be a multiple of 4 in bytes, {len} provided",
+        );
+        Err(InvalidLen::new(len).into())
+    }
+
+    #[inline]
+    pub fn password(&self) -> &[u8]
+    where
+        T: AsRef<[u8]>,
+    {
+        self.0.as_ref()
+    }
+}
+
+impl<T> Attribute for Password<T> {
+    #[inline]
+    fn attribute_type(&self) -> Type {
+        Self::TYPE
+    }
+}
+
+impl<T: AsRef<[u8]>> EncodeAttribute for Password<T> {
+    #[inline]
+    fn encoded_value_len(&self) -> u16 {
+        self.password().len() as u16
+    }
+
+    #[inline]
+    fn encode<'a>(&self, dst: &'a mut [u8], _: &TransactionId) -> Result<&'a mut [u8], StunError> {
+        super::encode_variable_len(Self::TYPE, self.password(), dst)
+    }
+}
+
+impl<'d> DecodeAttribute<'d> for Password<&'d [u8]> {
+    #[inline]
+    fn decode(_: Type, src: &'d [u8], _: &TransactionId) -> Result<Self, StunError> {
+        Self::new(src)
+    }
+}
@@ -18,6 +18,11 @@ impl<T> Data<T> {
     {
         self.0.as_ref()
     }
+
+    #[inline]
+    pub fn into_inner(self) -> T {
+        self.0
+    }
 }
 
 impl<T> Attribute for Data<T> {
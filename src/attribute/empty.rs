@@ -65,7 +65,7 @@ pub fn decode_empty(src: &[u8]) -> Result<(), impl ErrorKind> {
         InvalidParameter,
         "empty attribute requires an empty buffer , buffer of length {actual} provided",
     );
-    if !src.is_empty() {
+    if src.is_empty() {
         Ok(())
     } else {
         Err(LenMismatch::new(src.len()))
@@ -77,5 +77,14 @@ new_empty_attr!(UseCandidate, Type::USE_CANDIDATE);
 
 #[cfg(test)]
 mod test {
-    crate::new_empty_attr!(TestAttr, crate::attribute::Type::MAPPED_ADDRESS);
+    use super::*;
+    use crate::{attribute::DecodeAttribute, error::StunErrorKind, TransactionId};
+
+    #[test]
+    fn test_decode() {
+        let id = TransactionId::new([0; 12]);
+        assert!(DontFragment::decode(Type::DONT_FRAGMENT, &[], &id).is_ok());
+        let err = DontFragment::decode(Type::DONT_FRAGMENT, &[0; 4], &id).unwrap_err();
+        assert_eq!(err.error_kind(), StunErrorKind::InvalidParameter);
+    }
 }
@@ -0,0 +1,65 @@
+use super::{fixed, Attribute, DecodeAttribute, EncodeAttribute, StunError, TransactionId, Type};
+
+// https://datatracker.ietf.org/doc/html/rfc8656#section-18.4
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct EvenPort(bool);
+
+impl EvenPort {
+    pub const TYPE: Type = Type::EVEN_PORT;
+
+    const R: u8 = 0x80;
+
+    // whether the next higher port should be reserved as well
+    #[inline]
+    pub const fn new(reserve: bool) -> Self {
+        Self(reserve)
+    }
+
+    #[inline]
+    pub fn reserve(&self) -> bool {
+        self.0
+    }
+}
+
+impl Attribute for EvenPort {
+    #[inline]
+    fn attribute_type(&self) -> Type {
+        Self::TYPE
+    }
+}
+
+impl EncodeAttribute for EvenPort {
+    #[inline]
+    fn encoded_value_len(&self) -> u16 {
+        1
+    }
+
+    #[inline]
+    fn encode<'a>(&self, dst: &'a mut [u8], _: &TransactionId) -> Result<&'a mut [u8], StunError> {
+        fixed::encode_fixed(Self::TYPE, dst, &[if self.0 { Self::R } else { 0 }])
+    }
+}
+
+impl DecodeAttribute<'_> for EvenPort {
+    // the RFFU bits are ignored on receipt
+    #[inline]
+    fn decode(_: Type, src: &[u8], _: &TransactionId) -> Result<Self, StunError> {
+        let &[x] = fixed::decode_fixed(src)?;
+        Ok(Self(x & Self::R != 0))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_even_port() {
+        let (mut buf, id) = ([0xFF; 8], TransactionId::new([0; 12]));
+        assert!(EvenPort::new(true).encode(&mut buf, &id).unwrap().is_empty());
+        assert_eq!(buf, [0x00, 0x18, 0x00, 0x01, 0x80, 0x00, 0x00, 0x00]);
+        assert!(EvenPort::decode(Type::EVEN_PORT, &[0xFF], &id).unwrap().reserve());
+        assert!(!EvenPort::decode(Type::EVEN_PORT, &[0x7F], &id).unwrap().reserve());
+        assert!(EvenPort::decode(Type::EVEN_PORT, &[0x80, 0], &id).is_err());
+    }
+}
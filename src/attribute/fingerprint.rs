@@ -0,0 +1,105 @@
+use crate::{
+    attribute::Type,
+    error::{new_error, StunError},
+    parse::{Item, ValidationHeader},
+};
+
+crate::new_int_attr!(Fingerprint, Type::FINGERPRINT, u32);
+
+impl Fingerprint {
+    pub const XOR: u32 = 0x5354_554E;
+
+    #[inline]
+    pub fn calculate(header: &ValidationHeader, attrs_up_to: &[u8]) -> Self {
+        let mut crc = Crc32::new();
+        crc.update(header.as_bytes());
+        crc.update(attrs_up_to);
+        Self(crc.finalize() ^ Self::XOR)
+    }
+
+    pub fn verify(&self, mut header: ValidationHeader, item: &Item) -> Result<(), StunError> {
+        header.set_len_from_item(item);
+        let expected = Self::calculate(&header, item.attrs_up_to());
+        if expected == *self {
+            return Ok(());
+        }
+        new_error!(
+            FingerprintMismatch { expected: u32, actual: u32 },
+            ValidationFailed,
+            "fingerprint {actual:#010X} doesn't match the calculated {expected:#010X}",
+        );
+        Err(FingerprintMismatch::new(expected.0, self.0).into())
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Crc32(u32);
+
+impl Crc32 {
+    #[inline]
+    pub const fn new() -> Self {
+        Self(u32::MAX)
+    }
+
+    #[inline]
+    pub fn update(&mut self, src: &[u8]) {
+        for &b in src {
+            self.0 = CRC_TABLE[((self.0 ^ b as u32) & 0xFF) as usize] ^ (self.0 >> 8);
+        }
+    }
+
+    #[inline]
+    pub const fn finalize(self) -> u32 {
+        !self.0
+    }
+}
+
+// ISO-HDLC (reflected 0x04C11DB7), as used by ethernet and RFC 1952
+static CRC_TABLE: [u32; 256] = {
+    const POLY: u32 = 0xEDB8_8320;
+    let (mut table, mut i) = ([0; 256], 0);
+    while i < table.len() {
+        let (mut crc, mut bit) = (i as u32, 0);
+        while bit < u8::BITS {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ POLY } else { crc >> 1 };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{attribute::DecodeAttribute, test_data::*, MessageParser};
+
+    #[test]
+    fn test_crc32() {
+        let mut crc = Crc32::new();
+        crc.update(b"1234");
+        crc.update(b"56789");
+        assert_eq!(crc.finalize(), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn test_verify() {
+        let mut v = [core::mem::MaybeUninit::uninit(); 32];
+        for parts in &TEST_VECTOR[..3] {
+            let msg = assert_ok!(
+                MessageParser::from_complete_message(parts.message, &mut v),
+                "error parsing raw msg",
+            );
+            let item = msg.iter_raw().next_back().expect("fingerprint");
+            let fingerprint = assert_ok!(
+                Fingerprint::decode(item.attr(), item.value(), msg.transaction_id()),
+                "error decoding fingerprint",
+            );
+            assert_ok!(
+                fingerprint.verify(msg.validation_header(), &item),
+                "error verifying fingerprint",
+            );
+        }
+    }
+}
@@ -102,9 +102,4 @@ pub fn decode_fixed<const N: usize>(src: &[u8]) -> Result<&[u8; N], impl ErrorKi
 
 new_fixed_attr!(UserHash, Type::USERHASH, 32);
 new_fixed_attr!(MessageIntegrity, Type::MESSAGE_INTEGRITY, 20);
-new_fixed_attr!(MessageIntegritySha256, Type::MESSAGE_INTEGRITY_SHA256, 32);
-
-#[cfg(test)]
-mod test {
-    crate::new_fixed_attr!(TestAttr, crate::attribute::Type::MAPPED_ADDRESS, 32);
-}
+new_fixed_attr!(ReservationToken, Type::RESERVATION_TOKEN, 8);
@@ -98,11 +98,5 @@ pub fn decode_integer<T, F: FnOnce([u8; N]) -> T, const N: usize>(
 
 new_int_attr!(IceControlled, Type::ICE_CONTROLLED, u64);
 new_int_attr!(IceControlling, Type::ICE_CONTROLLING, u64);
-new_int_attr!(Fingerprint, Type::FINGERPRINT, u32);
 new_int_attr!(Priority, Type::PRIORITY, u32);
 new_int_attr!(Lifetime, Type::LIFETIME, u32);
-
-#[cfg(test)]
-mod test {
-    crate::new_int_attr!(TestAttr, crate::attribute::Type::MAPPED_ADDRESS, u32);
-}
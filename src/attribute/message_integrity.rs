@@ -0,0 +1,130 @@
+use super::{Attribute, DecodeAttribute, EncodeAttribute, StunError, TransactionId, Type};
+use crate::error::new_error;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MessageIntegritySha256<T = &'static [u8]>(T);
+
+impl<T> MessageIntegritySha256<T> {
+    pub const TYPE: Type = Type::MESSAGE_INTEGRITY_SHA256;
+
+    pub const MIN_LEN: usize = 16;
+
+    pub const MAX_LEN: usize = 32;
+
+    #[inline]
+    pub fn new(value: T) -> Result<Self, StunError>
+    where
+        T: AsRef<[u8]>,
+    {
+        validate_len(value.as_ref().len())?;
+        Ok(Self(value))
+    }
+
+    #[inline]
+    pub fn value(&self) -> &[u8]
+    where
+        T: AsRef<[u8]>,
+    {
+        self.0.as_ref()
+    }
+
+    #[inline]
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+
+    pub fn truncate(&self, len: usize) -> Result<MessageIntegritySha256<&[u8]>, StunError>
+    where
+        T: AsRef<[u8]>,
+    {
+        let value = self.value();
+        let Some(truncated) = value.get(..len) else {
+            new_error!(
+                TruncateTooLong { len: u16, actual: u16 },
+                InvalidParameter,
+                "can't truncate a MESSAGE-INTEGRITY-SHA256 of {actual} bytes to {len} bytes",
+            );
+            let len = len.try_into().unwrap_or(u16::MAX);
+            return Err(TruncateTooLong::new(len, value.len() as u16).into());
+        };
+        MessageIntegritySha256::new(truncated)
+    }
+}
+
+impl MessageIntegritySha256<[u8; 32]> {
+    #[inline]
+    pub const fn const_new(value: [u8; 32]) -> Self {
+        Self(value)
+    }
+}
+
+impl<T> Attribute for MessageIntegritySha256<T> {
+    #[inline]
+    fn attribute_type(&self) -> Type {
+        Self::TYPE
+    }
+}
+
+impl<T: AsRef<[u8]>> EncodeAttribute for MessageIntegritySha256<T> {
+    #[inline]
+    fn encoded_value_len(&self) -> u16 {
+        self.value().len() as u16
+    }
+
+    #[inline]
+    fn encode<'a>(&self, dst: &'a mut [u8], _: &TransactionId) -> Result<&'a mut [u8], StunError> {
+        super::encode_variable_len(Self::TYPE, self.value(), dst)
+    }
+}
+
+impl<'d> DecodeAttribute<'d> for MessageIntegritySha256<&'d [u8]> {
+    #[inline]
+    fn decode(_: Type, src: &'d [u8], _: &TransactionId) -> Result<Self, StunError> {
+        Self::new(src)
+    }
+}
+
+// https://datatracker.ietf.org/doc/html/rfc8489#section-14.6
+#[inline]
+pub(crate) fn validate_len(len: usize) -> Result<(), StunError> {
+    const MIN_LEN: usize = MessageIntegritySha256::<()>::MIN_LEN;
+    const MAX_LEN: usize = MessageIntegritySha256::<()>::MAX_LEN;
+    if (MIN_LEN..=MAX_LEN).contains(&len) && len % 4 == 0 {
+        return Ok(());
+    }
+    new_error!(
+        LenMismatch { actual: usize },
+        InvalidParameter,
+        "MESSAGE-INTEGRITY-SHA256 must be a multiple of 4 between {MIN_LEN} and {MAX_LEN} bytes, \
+        {actual} provided",
+    );
+    Err(LenMismatch::new(len).into())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::error::StunErrorKind;
+
+    #[test]
+    fn test_len() {
+        let buf = [0; 36];
+        let id = TransactionId::new([0; 12]);
+        for len in 0..buf.len() {
+            let res =
+                MessageIntegritySha256::decode(Type::MESSAGE_INTEGRITY_SHA256, &buf[..len], &id);
+            match len {
+                16 | 20 | 24 | 28 | 32 => assert_eq!(res.unwrap().value().len(), len),
+                _ => assert_eq!(res.unwrap_err().error_kind(), StunErrorKind::InvalidParameter),
+            }
+        }
+        let full = MessageIntegritySha256::const_new([0; 32]);
+        assert_eq!(full.truncate(20).unwrap().value(), &[0; 20]);
+        let truncated = full.truncate(16).unwrap();
+        assert_eq!(
+            truncated.truncate(20).unwrap_err().error_kind(),
+            StunErrorKind::InvalidParameter
+        );
+        assert_eq!(full.truncate(18).unwrap_err().error_kind(), StunErrorKind::InvalidParameter);
+    }
+}
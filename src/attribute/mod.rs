@@ -8,12 +8,19 @@ use crate::{
 pub mod addr;
 mod attr_enum;
 mod change_request;
+mod channel_number;
+mod classic;
 pub mod data;
 pub mod empty;
 pub mod error_code;
+mod even_port;
+pub mod fingerprint;
 pub mod fixed;
 pub mod integer;
+pub mod message_integrity;
+mod nat_behavior;
 pub mod password;
+mod requested_transport;
 pub mod string;
 mod r#type;
 pub mod unknown;
@@ -23,14 +30,15 @@ pub use rfc8445 as ice;
 pub use rfc8489 as stun;
 pub use rfc8656 as turn;
 pub use string::{AsStr, Validated};
-pub use unknown::UnknownAttribute;
+pub use unknown::{UnderstoodAttributes, UnknownAttribute};
 
 pub mod rfc8489 {
     pub use super::{
         addr::{AlternateServer, MappedAddress, XorMappedAddress},
         error_code::ErrorCode,
-        fixed::{MessageIntegrity, MessageIntegritySha256, UserHash},
-        integer::Fingerprint,
+        fingerprint::Fingerprint,
+        fixed::{MessageIntegrity, UserHash},
+        message_integrity::MessageIntegritySha256,
         password::{PasswordAlgorithm, PasswordAlgorithms},
         string::{AlternateDomain, Nonce, Realm, Software, Username},
         unknown::UnknownAttributes,
@@ -47,8 +55,7 @@ pub mod rfc8489 {
         pub type Username<'a> = super::Username<Validated<&'a str, false, false, true>>;
         pub type MessageIntegrity<'a> =
             super::MessageIntegrity<&'a [u8; super::MessageIntegrity::<()>::LEN]>;
-        pub type MessageIntegritySha256<'a> =
-            super::MessageIntegritySha256<&'a [u8; super::MessageIntegritySha256::<()>::LEN]>;
+        pub type MessageIntegritySha256<'a> = super::MessageIntegritySha256<&'a [u8]>;
         pub type UserHash<'a> = super::UserHash<&'a [u8; super::UserHash::<()>::LEN]>;
         pub type PasswordAlgorithm<'a> = super::PasswordAlgorithm<&'a [u8]>;
         pub type PasswordAlgorithms<'a> = super::PasswordAlgorithms<password::Parsed<'a>>;
@@ -56,8 +63,48 @@ pub mod rfc8489 {
     }
 }
 
+// https://datatracker.ietf.org/doc/html/rfc3489, only parsed in `ParseOptions::with_rfc3489`
+pub mod rfc3489 {
+    pub use super::{
+        addr::{ChangedAddress, MappedAddress, ReflectedFrom, ResponseAddress, SourceAddress},
+        change_request::ChangeRequest,
+        classic::Password,
+        error_code::ErrorCode,
+        fixed::MessageIntegrity,
+        unknown::UnknownAttributes,
+    };
+
+    pub mod parsed {
+        pub use super::{
+            ChangeRequest, ChangedAddress, MappedAddress, ReflectedFrom, ResponseAddress,
+            SourceAddress,
+        };
+        pub use crate::attribute::rfc8489::parsed::{
+            ErrorCode, MessageIntegrity, UnknownAttributes,
+        };
+        pub type Password<'a> = super::Password<&'a [u8]>;
+
+        crate::define_attribute_enum!(ClassicAttribute<'a>, [
+            MappedAddress,
+            ResponseAddress,
+            ChangeRequest,
+            SourceAddress,
+            ChangedAddress,
+            Password<'a>,
+            MessageIntegrity<'a>,
+            ErrorCode<'a>,
+            UnknownAttributes<'a>,
+            ReflectedFrom,
+        ]);
+    }
+}
+
 pub mod rfc5780 {
-    pub use super::change_request::ChangeRequest;
+    pub use super::{
+        addr::{OtherAddress, ResponseOrigin},
+        change_request::ChangeRequest,
+        nat_behavior::{Padding, ResponsePort},
+    };
 }
 
 pub mod rfc8445 {
@@ -72,17 +119,21 @@ pub mod rfc8656 {
         addr::{
             AdditionalAddressFamily, RequestedAddressFamily, XorPeerAddress, XorRelayedAddress,
         },
+        channel_number::ChannelNumber,
         data::Data,
         empty::DontFragment,
         error_code::AddressErrorCode,
+        even_port::EvenPort,
+        fixed::ReservationToken,
         integer::Lifetime,
+        requested_transport::RequestedTransport,
     };
 
     pub mod parsed {
-        pub use super::Lifetime;
+        pub use super::{ChannelNumber, EvenPort, Lifetime, RequestedTransport};
         pub type Data<'a> = super::Data<&'a [u8]>;
-
-        crate::new_int_attr!(RequestedTransport, crate::attribute::Type::REQUESTED_TRANSPORT, u32);
+        pub type ReservationToken<'a> =
+            super::ReservationToken<&'a [u8; super::ReservationToken::<()>::LEN]>;
     }
 }
 
@@ -176,12 +227,39 @@ new_error!(
 
 #[cfg(test)]
 mod test {
-    use super::{rfc8489::*, *};
+    use super::*;
     use crate::{
         test_data::{assert_ok, TEST_VECTOR},
         MessageParser,
     };
 
+    mod attributes {
+        use crate::attribute::{ice::*, stun::parsed::*};
+
+        crate::define_attribute_enum!(Attributes<'a>, [
+            MappedAddress,
+            XorMappedAddress,
+            AlternateServer,
+            Fingerprint,
+            ErrorCode<'a>,
+            AlternateDomain<'a>,
+            Nonce<'a>,
+            Software<'a>,
+            Realm<'a>,
+            Username<'a>,
+            MessageIntegrity<'a>,
+            MessageIntegritySha256<'a>,
+            UserHash<'a>,
+            PasswordAlgorithm<'a>,
+            PasswordAlgorithms<'a>,
+            UnknownAttributes<'a>,
+            Priority,
+            IceControlled,
+            IceControlling,
+            UseCandidate,
+        ]);
+    }
+
     #[test]
     fn test_iter() {
         let mut v = [core::mem::MaybeUninit::uninit(); 32];
@@ -189,9 +267,9 @@ mod test {
             MessageParser::from_complete_message(TEST_VECTOR[3].message, &mut v),
             "error parsing raw msg",
         );
-        let id = TransactionId::new([0; 12]);
         let attributes = msg.iter().collect::<Result<Vec<attributes::Attributes>, _>>().unwrap();
         println!("{attributes:?}");
+        assert!(msg.check_comprehension(attributes::Attributes::TYPES, &mut []).is_none());
         let msg = assert_ok!(
             MessageParser::from_complete_message(TEST_VECTOR[4].message, &mut v),
             "error parsing raw msg",
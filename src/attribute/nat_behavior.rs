@@ -0,0 +1,142 @@
+use super::{
+    encode_type_length, ensure_space, integer, total_len, Attribute, DecodeAttribute,
+    EncodeAttribute, StunError, TransactionId, Type, ValueTooLong, MAX_VALUE_LEN,
+};
+
+// https://datatracker.ietf.org/doc/html/rfc5780#section-7.5
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ResponsePort(u16);
+
+impl ResponsePort {
+    pub const TYPE: Type = Type::RESPONSE_PORT;
+
+    #[inline]
+    pub const fn new(port: u16) -> Self {
+        Self(port)
+    }
+
+    #[inline]
+    pub fn port(&self) -> u16 {
+        self.0
+    }
+}
+
+impl Attribute for ResponsePort {
+    #[inline]
+    fn attribute_type(&self) -> Type {
+        Self::TYPE
+    }
+}
+
+impl EncodeAttribute for ResponsePort {
+    #[inline]
+    fn encoded_value_len(&self) -> u16 {
+        LEN as _
+    }
+
+    #[inline]
+    fn encode<'a>(&self, dst: &'a mut [u8], _: &TransactionId) -> Result<&'a mut [u8], StunError> {
+        integer::encode_integer::<_, LEN, TOTAL_LEN>(Self::TYPE, dst, || {
+            ((self.0 as u32) << 16).to_be_bytes()
+        })
+    }
+}
+
+impl DecodeAttribute<'_> for ResponsePort {
+    // the padding is ignored on receipt
+    #[inline]
+    fn decode(_: Type, src: &[u8], _: &TransactionId) -> Result<Self, StunError> {
+        let x = integer::decode_integer(src, u32::from_be_bytes)?;
+        Ok(Self((x >> 16) as u16))
+    }
+}
+
+// https://datatracker.ietf.org/doc/html/rfc5780#section-7.6
+// Only the length is kept, the value is zeroes on encoding and ignored on decoding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Padding(u16);
+
+impl Padding {
+    pub const TYPE: Type = Type::PADDING;
+
+    #[inline]
+    pub fn new(len: usize) -> Result<Self, StunError> {
+        if len > MAX_VALUE_LEN {
+            return Err(ValueTooLong::new(len).into());
+        }
+        Ok(Self(len as u16))
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.0 as usize
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+}
+
+impl Attribute for Padding {
+    #[inline]
+    fn attribute_type(&self) -> Type {
+        Self::TYPE
+    }
+}
+
+impl EncodeAttribute for Padding {
+    #[inline]
+    fn encoded_value_len(&self) -> u16 {
+        self.0
+    }
+
+    fn encode<'a>(&self, dst: &'a mut [u8], _: &TransactionId) -> Result<&'a mut [u8], StunError> {
+        let total_len = total_len(self.0);
+        ensure_space(total_len, self.0, dst.len())?;
+        let (tlvp, rest) = dst.split_at_mut(total_len);
+        encode_type_length(Self::TYPE, self.0, tlvp).fill(0);
+        Ok(rest)
+    }
+}
+
+impl DecodeAttribute<'_> for Padding {
+    #[inline]
+    fn decode(_: Type, src: &[u8], _: &TransactionId) -> Result<Self, StunError> {
+        Self::new(src.len())
+    }
+}
+
+const LEN: usize = core::mem::size_of::<u32>();
+const TOTAL_LEN: usize = LEN + super::RawAttribute::TL_LEN;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::error::StunErrorKind;
+
+    #[test]
+    fn test_response_port() {
+        let (mut buf, id) = ([0xFF; 8], TransactionId::new([0; 12]));
+        assert!(ResponsePort::new(3479).encode(&mut buf, &id).unwrap().is_empty());
+        assert_eq!(buf, [0x00, 0x27, 0x00, 0x04, 0x0D, 0x97, 0x00, 0x00]);
+        let decoded = ResponsePort::decode(Type::RESPONSE_PORT, &[0x0D, 0x97, 0xFF, 0xFF], &id);
+        assert_eq!(decoded.unwrap().port(), 3479);
+    }
+
+    #[test]
+    fn test_padding() {
+        let (mut buf, id) = ([0xFF; 16], TransactionId::new([0; 12]));
+        let padding = Padding::new(5).unwrap();
+        assert_eq!(padding.encoded_len(), 12);
+        assert_eq!(padding.encode(&mut buf, &id).unwrap().len(), 4);
+        assert_eq!(&buf[..12], &[0x00, 0x26, 0x00, 0x05, 0, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(Padding::decode(Type::PADDING, &buf[4..9], &id).unwrap(), padding);
+        let err = Padding::new(0x1000).unwrap().encode(&mut buf, &id).unwrap_err();
+        assert_eq!(err.error_kind(), StunErrorKind::BufferTooSmall);
+        assert_eq!(
+            Padding::new(MAX_VALUE_LEN + 1).unwrap_err().error_kind(),
+            StunErrorKind::ValueTooLong
+        );
+    }
+}
@@ -33,7 +33,7 @@ impl<'a> PasswordAlgorithms<Parsed<'a>> {
     }
 
     #[inline]
-    pub fn iter(&self) -> PasswordAlgorithmIter {
+    pub fn iter(&self) -> PasswordAlgorithmIter<'_> {
         PasswordAlgorithmIter(self.inner.0)
     }
 }
@@ -92,7 +92,7 @@ impl<U: AsRef<[u8]>, T: AsRef<[PasswordAlgorithm<U>]>> EncodeAttribute
         value_len(self.inner.as_ref())
     }
 
-    fn encode<'a>(&self, dst: &'a mut [u8], t: &TransactionId) -> Result<&'a mut [u8], StunError> {
+    fn encode<'a>(&self, dst: &'a mut [u8], _: &TransactionId) -> Result<&'a mut [u8], StunError> {
         let algorithms = self.inner.as_ref();
         let len = value_len(algorithms);
         let total_len = super::total_len(len);
@@ -101,15 +101,10 @@ impl<U: AsRef<[u8]>, T: AsRef<[PasswordAlgorithm<U>]>> EncodeAttribute
         let (tl, mut vp) = util::split_array_mut(tlvp);
         RawAttribute::encode_type_length(Self::TYPE, len, tl);
         for alg in algorithms {
-            vp = alg.encode(vp, t)?;
-        }
-        new_error!(
-            PassAlgoLenMismatch,
-            InvalidParameter,
-            "something went wrong encoding password algorithms",
-        );
-        if !vp.is_empty() {
-            return Err(PassAlgoLenMismatch.into());
+            let padded_len = RawAttribute::padded_len(alg.encoded_value_len()) as usize;
+            let (value, next) = vp.split_at_mut(padded_len.min(vp.len()));
+            alg.encode_value(value)?;
+            vp = next;
         }
         Ok(rest)
     }
@@ -183,26 +178,34 @@ impl<T: AsRef<[u8]>> EncodeAttribute for PasswordAlgorithm<T> {
     }
 
     fn encode<'a>(&self, dst: &'a mut [u8], _: &TransactionId) -> Result<&'a mut [u8], StunError> {
+        let len = self.encoded_value_len();
+        let total_len = super::total_len(len);
+        super::ensure_space(total_len, len, dst.len())?;
+        let (attr, rest) = dst.split_at_mut(total_len);
+        let (tl, value) = util::split_array_mut(attr);
+        RawAttribute::encode_type_length(Self::TYPE, len, tl);
+        self.encode_value(value)?;
+        Ok(rest)
+    }
+}
+
+impl<T: AsRef<[u8]>> PasswordAlgorithm<T> {
+    // The algorithm, parameters length and padded parameters, without the type and length of the
+    // attribute, as PASSWORD-ALGORITHMS lists them
+    fn encode_value(&self, dst: &mut [u8]) -> Result<(), StunError> {
         let parameters = self.parameters();
         if parameters.len() > super::MAX_VALUE_LEN - HEADER_LEN {
             new_error!(ParamsTooLong, ValueTooLong, "algorithm parameters are absurdly long");
             return Err(ParamsTooLong.into());
         }
-        let param_len = parameters.len() as u16;
-        let len = HEADER_LEN as u16 + param_len;
-        let total_len = super::total_len(len);
-        super::ensure_space(total_len, len, dst.len())?;
-        let (attr, rest) = dst.split_at_mut(total_len);
-        let (headers, vp) = util::split_array_mut::<_, { RawAttribute::TL_LEN + HEADER_LEN }>(attr);
-        let (tl, header): (_, &mut [u8; HEADER_LEN]) = util::split_array_exact_mut(headers);
+        let (header, vp) = util::split_array_mut::<_, HEADER_LEN>(dst);
         let (alg, pl) = util::split_array_exact_mut(header);
-        RawAttribute::encode_type_length(Self::TYPE, len, tl);
         *alg = self.algorithm.0.to_be_bytes();
-        *pl = param_len.to_be_bytes();
+        *pl = (parameters.len() as u16).to_be_bytes();
         let (parameters_buf, pad) = vp.split_at_mut(parameters.len());
         parameters_buf.copy_from_slice(parameters);
         pad.fill(0);
-        Ok(rest)
+        Ok(())
     }
 }
 
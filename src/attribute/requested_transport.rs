@@ -0,0 +1,85 @@
+use super::{integer, Attribute, DecodeAttribute, EncodeAttribute, StunError, TransactionId, Type};
+use crate::error::new_error;
+
+// https://datatracker.ietf.org/doc/html/rfc8656#section-18.3
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct RequestedTransport(u8);
+
+impl RequestedTransport {
+    pub const TYPE: Type = Type::REQUESTED_TRANSPORT;
+
+    // https://www.iana.org/assignments/protocol-numbers/protocol-numbers.xhtml
+    pub const TCP: u8 = 6;
+
+    pub const UDP: u8 = 17;
+
+    #[inline]
+    pub const fn new(protocol: u8) -> Self {
+        Self(protocol)
+    }
+
+    #[inline]
+    pub fn protocol(&self) -> u8 {
+        self.0
+    }
+}
+
+impl Attribute for RequestedTransport {
+    #[inline]
+    fn attribute_type(&self) -> Type {
+        Self::TYPE
+    }
+}
+
+impl EncodeAttribute for RequestedTransport {
+    #[inline]
+    fn encoded_value_len(&self) -> u16 {
+        LEN as _
+    }
+
+    #[inline]
+    fn encode<'a>(&self, dst: &'a mut [u8], _: &TransactionId) -> Result<&'a mut [u8], StunError> {
+        integer::encode_integer::<_, LEN, TOTAL_LEN>(Self::TYPE, dst, || [self.0, 0, 0, 0])
+    }
+}
+
+impl DecodeAttribute<'_> for RequestedTransport {
+    #[inline]
+    fn decode(_: Type, src: &[u8], _: &TransactionId) -> Result<Self, StunError> {
+        let [protocol, rffu @ ..] = integer::decode_integer(src, |x: [u8; LEN]| x)?;
+        if rffu == [0; 3] {
+            return Ok(Self(protocol));
+        }
+        new_error!(
+            NonZeroRffu { rffu: u32 },
+            InvalidParameter,
+            "REQUESTED-TRANSPORT RFFU bytes must be zero, got {rffu:#08X}",
+        );
+        Err(NonZeroRffu::new(u32::from_be_bytes([0, rffu[0], rffu[1], rffu[2]])).into())
+    }
+}
+
+const LEN: usize = core::mem::size_of::<u32>();
+const TOTAL_LEN: usize = LEN + super::RawAttribute::TL_LEN;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::error::StunErrorKind;
+
+    #[test]
+    fn test_requested_transport() {
+        let (mut buf, id) = ([0xFF; 8], TransactionId::new([0; 12]));
+        let udp = RequestedTransport::new(RequestedTransport::UDP);
+        assert!(udp.encode(&mut buf, &id).unwrap().is_empty());
+        assert_eq!(buf, [0x00, 0x19, 0x00, 0x04, 17, 0, 0, 0]);
+        let decoded = RequestedTransport::decode(Type::REQUESTED_TRANSPORT, &buf[4..], &id);
+        assert_eq!(decoded.unwrap(), udp);
+        let tcp = RequestedTransport::decode(Type::REQUESTED_TRANSPORT, &[6, 0, 0, 0], &id);
+        assert_eq!(tcp.unwrap().protocol(), RequestedTransport::TCP);
+        let err = RequestedTransport::decode(Type::REQUESTED_TRANSPORT, &[17, 0, 1, 0], &id);
+        assert_eq!(err.unwrap_err().error_kind(), StunErrorKind::InvalidParameter);
+        let err = RequestedTransport::decode(Type::REQUESTED_TRANSPORT, &[17], &id);
+        assert_eq!(err.unwrap_err().error_kind(), StunErrorKind::InvalidParameter);
+    }
+}
@@ -6,6 +6,8 @@ mod quoted;
 mod text_attr;
 mod validated;
 
+#[cfg(feature = "auth")]
+pub(crate) use opaque::is_opaque_string;
 pub use text_attr::{AlternateDomain, Nonce, Realm, Software, Username};
 pub use validated::Validated;
 
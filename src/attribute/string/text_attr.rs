@@ -123,8 +123,3 @@ new_text_attr!(Username, Type::USERNAME, false, false, true);
 new_text_attr!(Nonce, Type::NONCE, false, true, false);
 new_text_attr!(Software, Type::SOFTWARE, false, false, false);
 new_text_attr!(AlternateDomain, Type::ALTERNATE_DOMAIN, true, false, false);
-
-#[cfg(test)]
-mod test {
-    new_text_attr!(TestAttr, crate::attribute::Type::REALM, true, true, true);
-}
@@ -40,11 +40,16 @@ macro_rules! codepoints {
 // https://www.iana.org/assignments/stun-parameters/stun-parameters.xhtml#stun-parameters-4
 codepoints! {
     (0x0001, MAPPED_ADDRESS, "MAPPED-ADDRESS"),
+    (0x0002, RESPONSE_ADDRESS, "RESPONSE-ADDRESS"),
     (0x0003, CHANGE_REQUEST, "CHANGE-REQUEST"),
+    (0x0004, SOURCE_ADDRESS, "SOURCE-ADDRESS"),
+    (0x0005, CHANGED_ADDRESS, "CHANGED-ADDRESS"),
     (0x0006, USERNAME, "USERNAME"),
+    (0x0007, PASSWORD, "PASSWORD"),
     (0x0008, MESSAGE_INTEGRITY, "MESSAGE-INTEGRITY"),
     (0x0009, ERROR_CODE, "ERROR-CODE"),
     (0x000A, UNKNOWN_ATTRIBUTES, "UNKNOWN-ATTRIBUTES"),
+    (0x000B, REFLECTED_FROM, "REFLECTED-FROM"),
     (0x000C, CHANNEL_NUMBER, "CHANNEL-NUMBER"),
     (0x000D, LIFETIME, "LIFETIME"),
     (0x0012, XOR_PEER_ADDRESS, "XOR-PEER-ADDRESS"),
@@ -167,6 +167,31 @@ const fn convert(&b: &TypeBuffer) -> Type {
 
 type TypeBuffer = [u8; Type::LEN];
 
+pub trait UnderstoodAttributes {
+    fn understands(&self, attr: Type) -> bool;
+}
+
+impl UnderstoodAttributes for [Type] {
+    #[inline]
+    fn understands(&self, attr: Type) -> bool {
+        self.contains(&attr)
+    }
+}
+
+impl<const N: usize> UnderstoodAttributes for [Type; N] {
+    #[inline]
+    fn understands(&self, attr: Type) -> bool {
+        self.contains(&attr)
+    }
+}
+
+impl<F: Fn(Type) -> bool> UnderstoodAttributes for F {
+    #[inline]
+    fn understands(&self, attr: Type) -> bool {
+        self(attr)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct UnknownAttribute<'a> {
     pub(crate) attr: Type,
@@ -0,0 +1,300 @@
+use crate::{
+    attribute::{
+        password::Algorithm,
+        rfc8489::{
+            parsed, Nonce, PasswordAlgorithm, PasswordAlgorithms, Realm, UserHash, Username,
+        },
+        Type,
+    },
+    auth::{LongTermKey, SecurityFeatures},
+    build::{Buffer, MessageBuilder},
+    error::{new_error, StunError},
+    parse::MessageParser,
+};
+use alloc::{string::String, vec::Vec};
+
+// https://datatracker.ietf.org/doc/html/rfc8489#section-9.2
+// The client side of the long-term credential mechanism. Requests are first sent without
+// credentials, `handle_error` learns the realm, nonce and password algorithm from the 401 or 438
+// response and tells whether to retry, and `add_credentials` adds them to the retried request.
+#[derive(Debug, Clone)]
+pub struct ClientAuthenticator {
+    username: Username<String>,
+    password: String,
+    state: Option<State>,
+}
+
+#[derive(Debug, Clone)]
+struct State {
+    realm: Realm<String>,
+    nonce: Nonce<String>,
+    features: Option<SecurityFeatures>,
+    algorithms: Vec<PasswordAlgorithm<Vec<u8>>>,
+    algorithm: Option<PasswordAlgorithm<Vec<u8>>>,
+    user_hash: Option<UserHash<[u8; 32]>>,
+    key: LongTermKey,
+}
+
+impl ClientAuthenticator {
+    pub fn new(username: &str, password: &str) -> Result<Self, StunError> {
+        Username::new(username).validate()?;
+        Ok(Self {
+            username: Username::new(username.into()),
+            password: password.into(),
+            state: None,
+        })
+    }
+
+    #[inline]
+    pub fn username(&self) -> &str {
+        self.username.as_str()
+    }
+
+    #[inline]
+    pub fn realm(&self) -> Option<&str> {
+        self.state.as_ref().map(|state| state.realm.as_str())
+    }
+
+    #[inline]
+    pub fn nonce(&self) -> Option<&str> {
+        self.state.as_ref().map(|state| state.nonce.as_str())
+    }
+
+    #[inline]
+    pub fn key(&self) -> Option<&LongTermKey> {
+        self.state.as_ref().map(|state| &state.key)
+    }
+
+    // https://datatracker.ietf.org/doc/html/rfc8489#section-9.2.5
+    // Returns whether the request should be retried with `add_credentials`. `retried` tells whether
+    // the failed request was itself a retry, which is only retried again if the nonce changed.
+    pub fn handle_error(&mut self, msg: &MessageParser, retried: bool) -> Result<bool, StunError> {
+        let code = msg.find::<parsed::ErrorCode>(Type::ERROR_CODE)?.map(|code| code.code());
+        let (Some(code @ (401 | 438)), Some(nonce)) =
+            (code, msg.find::<parsed::Nonce>(Type::NONCE)?)
+        else {
+            return Ok(false);
+        };
+        let previous = self.state.as_ref();
+        if retried && previous.is_some_and(|state| state.nonce.as_str() == nonce.as_str()) {
+            return Ok(false);
+        }
+        // A 438 may leave out the realm, which stays the same
+        let realm = match (msg.find::<parsed::Realm>(Type::REALM)?, previous) {
+            (Some(realm), _) => String::from(realm.as_str()),
+            (None, Some(state)) if code == 438 => state.realm.as_str().into(),
+            (None, _) => return Ok(false),
+        };
+        let features = SecurityFeatures::from_nonce(nonce.as_str());
+        let mut algorithms = Vec::new();
+        if features.is_some_and(|features| features.contains(SecurityFeatures::PASSWORD_ALGORITHMS))
+        {
+            // Missing or changed algorithms mean an attacker is bidding the algorithm down
+            let Some(offered) =
+                msg.find::<parsed::PasswordAlgorithms>(Type::PASSWORD_ALGORITHMS)?
+            else {
+                return Ok(false);
+            };
+            let offered = offered
+                .iter()
+                .map(|alg| PasswordAlgorithm::new(alg.algorithm(), alg.parameters().to_vec()));
+            algorithms.extend(offered);
+            if previous
+                .is_some_and(|state| !state.algorithms.is_empty() && state.algorithms != algorithms)
+            {
+                return Ok(false);
+            }
+        }
+        let algorithm = match algorithms.iter().find(|alg| is_supported(alg.algorithm())) {
+            Some(algorithm) => Some(algorithm.clone()),
+            None if algorithms.is_empty() => None,
+            None => return Ok(false),
+        };
+        let realm = Realm::new(realm);
+        let key = LongTermKey::from_password_algorithm(
+            algorithm.as_ref(),
+            &self.username,
+            &realm,
+            &self.password,
+        )?;
+        let anonymous = features
+            .is_some_and(|features| features.contains(SecurityFeatures::USERNAME_ANONYMITY));
+        let user_hash = match anonymous {
+            true => Some(UserHash::calculate(&self.username, &realm)?),
+            false => None,
+        };
+        let nonce = Nonce::new(nonce.as_str().into());
+        self.state = Some(State { realm, nonce, features, algorithms, algorithm, user_hash, key });
+        Ok(true)
+    }
+
+    // https://datatracker.ietf.org/doc/html/rfc8489#section-9.2.4
+    // Adds nothing until a challenge was handled. The integrity attribute goes last, so only
+    // FINGERPRINT may be added afterwards. RFC 5389 servers, whose nonces lack the cookie, get
+    // MESSAGE-INTEGRITY and RFC 8489 servers MESSAGE-INTEGRITY-SHA256.
+    pub fn add_credentials<B: Buffer + ?Sized>(
+        &self,
+        builder: &mut MessageBuilder<'_, B>,
+    ) -> Result<(), StunError> {
+        let Some(state) = &self.state else {
+            return Ok(());
+        };
+        match &state.user_hash {
+            Some(user_hash) => builder.add(user_hash)?,
+            None => builder.add(&self.username)?,
+        };
+        builder.add(&state.realm)?.add(&state.nonce)?;
+        if let Some(algorithm) = &state.algorithm {
+            let algorithms = PasswordAlgorithms::<_, Vec<u8>>::new(state.algorithms.as_slice());
+            builder.add(&algorithms)?.add(algorithm)?;
+        }
+        match state.features {
+            Some(_) => builder.add_message_integrity_sha256(state.key.as_ref())?,
+            None => builder.add_message_integrity(state.key.as_ref())?,
+        };
+        Ok(())
+    }
+
+    // Responses to requests with credentials must be protected with the same key
+    pub fn verify(&self, msg: &MessageParser) -> Result<(), StunError> {
+        let Some(state) = &self.state else {
+            return Ok(());
+        };
+        let key = state.key.as_ref();
+        if msg.verify_message_integrity_sha256(key)?.is_some()
+            || msg.verify_message_integrity(key)?.is_some()
+        {
+            return Ok(());
+        }
+        new_error!(MissingIntegrity, ValidationFailed, "the response isn't integrity protected");
+        Err(MissingIntegrity.into())
+    }
+}
+
+#[inline]
+fn is_supported(algorithm: Algorithm) -> bool {
+    matches!(algorithm, Algorithm::MD5 | Algorithm::SHA256)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        attribute::rfc8489::ErrorCode, error::StunErrorKind, test_data::*, Class, Method,
+        TransactionId,
+    };
+    use core::mem::MaybeUninit;
+
+    const PASSWORD: &str = "TheMatrIX";
+    const NONCE: &str = "obMatJos2AAADf//499k954d6OL34oL9FSTvy64sA";
+
+    fn response<'a>(
+        buf: &'a mut [u8],
+        code: u16,
+        realm: Option<&str>,
+        nonce: &str,
+        algorithms: &[PasswordAlgorithm<&[u8]>],
+    ) -> &'a mut [u8] {
+        let id = TransactionId::new([1; 12]);
+        let mut builder =
+            MessageBuilder::new(Class::ErrorResponse, Method::BINDING, id, buf).unwrap();
+        builder.add(&ErrorCode::from_status_code(code).unwrap()).unwrap();
+        if let Some(realm) = realm {
+            builder.add(&Realm::new(realm)).unwrap();
+        }
+        builder.add(&Nonce::new(nonce)).unwrap();
+        if !algorithms.is_empty() {
+            builder.add(&PasswordAlgorithms::<_, &[u8]>::new(algorithms)).unwrap();
+        }
+        builder.finish()
+    }
+
+    fn handle_error(auth: &mut ClientAuthenticator, src: &[u8], retried: bool) -> bool {
+        let mut v = [MaybeUninit::uninit(); 8];
+        let msg = assert_ok!(MessageParser::from_complete_message(src, &mut v), "bad response");
+        assert_ok!(auth.handle_error(&msg, retried), "error handling response")
+    }
+
+    #[test]
+    fn test_rfc8489() {
+        let (mut auth, mut arr) =
+            (ClientAuthenticator::new(LONG_TERM_USERNAME, PASSWORD).unwrap(), [0; 256]);
+        let algorithms = [
+            PasswordAlgorithm::new(Algorithm::new(3), &[][..]),
+            PasswordAlgorithm::new(Algorithm::SHA256, &[]),
+            PasswordAlgorithm::new(Algorithm::MD5, &[]),
+        ];
+        let src = response(&mut arr, 401, Some(LONG_TERM_REALM), NONCE, &algorithms);
+        assert!(handle_error(&mut auth, src, false));
+        assert!(!handle_error(&mut auth, src, true));
+        let (username, realm) = (Username::new(LONG_TERM_USERNAME), Realm::new(LONG_TERM_REALM));
+        let key = LongTermKey::new(Algorithm::SHA256, &username, &realm, PASSWORD).unwrap();
+        assert_eq!(
+            (auth.realm(), auth.nonce(), auth.key()),
+            (Some(LONG_TERM_REALM), Some(NONCE), Some(&key))
+        );
+
+        let (mut buf, mut v) = ([0; 512], [MaybeUninit::uninit(); 16]);
+        let id = TransactionId::new([2; 12]);
+        let mut builder =
+            MessageBuilder::new(Class::Request, Method::BINDING, id, &mut buf).unwrap();
+        assert_ok!(auth.add_credentials(&mut builder), "error adding credentials");
+        let encoded = builder.finish();
+        let msg = assert_ok!(MessageParser::from_complete_message(encoded, &mut v), "bad request");
+        let user_hash = msg.find::<parsed::UserHash>(Type::USERHASH).unwrap().expect("user hash");
+        assert_eq!(user_hash.value(), UserHash::calculate(&username, &realm).unwrap().value());
+        assert!(msg.find::<parsed::Username>(Type::USERNAME).unwrap().is_none());
+        let algorithm = msg.find::<parsed::PasswordAlgorithm>(Type::PASSWORD_ALGORITHM).unwrap();
+        assert_eq!(algorithm.expect("algorithm").algorithm(), Algorithm::SHA256);
+        let offered = msg.find::<parsed::PasswordAlgorithms>(Type::PASSWORD_ALGORITHMS).unwrap();
+        assert!(offered.expect("algorithms").iter().eq(algorithms.iter().cloned()));
+        let integrity = assert_ok!(msg.verify_message_integrity_sha256(key.as_ref()), "bad mac");
+        assert!(integrity.is_some());
+        assert_ok!(auth.verify(&msg), "bad integrity");
+
+        // a stale nonce keeps the realm, and a retried request is retried if the nonce changed
+        let nonce = "obMatJos2AAADstale";
+        let src = response(&mut arr, 438, None, nonce, &algorithms);
+        assert!(handle_error(&mut auth, src, true));
+        assert_eq!((auth.realm(), auth.nonce()), (Some(LONG_TERM_REALM), Some(nonce)));
+        let src = response(&mut arr, 401, Some(LONG_TERM_REALM), NONCE, &algorithms[1..]);
+        assert!(!handle_error(&mut auth, src, false));
+        let src = response(&mut arr, 401, Some(LONG_TERM_REALM), NONCE, &[]);
+        assert!(!handle_error(&mut auth, src, false));
+        let err =
+            auth.verify(&MessageParser::from_complete_message(src, &mut v).unwrap()).unwrap_err();
+        assert_eq!(err.error_kind(), StunErrorKind::ValidationFailed);
+    }
+
+    #[test]
+    fn test_rfc5389() {
+        let (mut auth, mut arr) = (ClientAuthenticator::new("user", "password").unwrap(), [0; 256]);
+        let src = response(&mut arr, 401, None, "f//499k954d6OL34oL9FSTvy64sA", &[]);
+        assert!(!handle_error(&mut auth, src, false));
+        let src = response(&mut arr, 400, Some("realm"), "f//499k954d6OL34oL9FSTvy64sA", &[]);
+        assert!(!handle_error(&mut auth, src, false));
+        let mut v = [MaybeUninit::uninit(); 8];
+        assert_ok!(
+            auth.verify(&MessageParser::from_complete_message(src, &mut v).unwrap()),
+            "bad integrity"
+        );
+        let src = response(&mut arr, 401, Some("realm"), "f//499k954d6OL34oL9FSTvy64sA", &[]);
+        assert!(handle_error(&mut auth, src, false));
+        let (username, realm) = (Username::new("user"), Realm::new("realm"));
+        let key = LongTermKey::new(Algorithm::MD5, &username, &realm, "password").unwrap();
+        assert_eq!(auth.key(), Some(&key));
+
+        let mut buf = [0; 256];
+        let id = TransactionId::new([2; 12]);
+        let mut builder =
+            MessageBuilder::new(Class::Request, Method::BINDING, id, &mut buf).unwrap();
+        assert_ok!(auth.add_credentials(&mut builder), "error adding credentials");
+        let encoded = builder.finish();
+        let msg = assert_ok!(MessageParser::from_complete_message(encoded, &mut v), "bad request");
+        let user = msg.find::<parsed::Username>(Type::USERNAME).unwrap().expect("username");
+        assert_eq!(user.as_str(), "user");
+        assert!(msg.find::<parsed::PasswordAlgorithm>(Type::PASSWORD_ALGORITHM).unwrap().is_none());
+        assert!(assert_ok!(msg.verify_message_integrity(key.as_ref()), "bad integrity").is_some());
+        assert!(ClientAuthenticator::new("user\u{7}", "password").is_err());
+    }
+}
@@ -0,0 +1,314 @@
+use crate::{
+    attribute::{
+        message_integrity,
+        rfc8489::{parsed, MessageIntegrity, MessageIntegritySha256},
+        DecodeAttribute, Type,
+    },
+    build::{Buffer, MessageBuilder},
+    error::{new_error, StunError},
+    parse::{Item, MessageParser, RawAttribute, ValidationHeader},
+};
+use core::borrow::Borrow;
+use hmac::{digest::KeyInit, Hmac, Mac};
+use sha1::Sha1;
+use sha2::Sha256;
+
+type HmacSha1 = Hmac<Sha1>;
+type HmacSha256 = Hmac<Sha256>;
+
+impl MessageIntegrity<[u8; 20]> {
+    #[inline]
+    pub fn calculate(header: &ValidationHeader, attrs_up_to: &[u8], key: &[u8]) -> Self {
+        Self::new(mac::<HmacSha1>(header, attrs_up_to, key).finalize().into_bytes().into())
+    }
+}
+
+impl<T: Borrow<[u8; 20]>> MessageIntegrity<T> {
+    pub fn verify(
+        &self,
+        mut header: ValidationHeader,
+        item: &Item,
+        key: &[u8],
+    ) -> Result<(), StunError> {
+        header.set_len_from_item(item);
+        mac::<HmacSha1>(&header, item.attrs_up_to(), key)
+            .verify_slice(self.value())
+            .map_err(|_| IntegrityMismatch::new(item.attr()).into())
+    }
+}
+
+impl MessageIntegritySha256<[u8; 32]> {
+    #[inline]
+    pub fn calculate(header: &ValidationHeader, attrs_up_to: &[u8], key: &[u8]) -> Self {
+        let mac = mac::<HmacSha256>(header, attrs_up_to, key);
+        Self::const_new(mac.finalize().into_bytes().into())
+    }
+}
+
+impl<T: AsRef<[u8]>> MessageIntegritySha256<T> {
+    pub fn verify(
+        &self,
+        mut header: ValidationHeader,
+        item: &Item,
+        key: &[u8],
+    ) -> Result<(), StunError> {
+        header.set_len_from_item(item);
+        mac::<HmacSha256>(&header, item.attrs_up_to(), key)
+            .verify_truncated_left(self.value())
+            .map_err(|_| IntegrityMismatch::new(item.attr()).into())
+    }
+}
+
+impl<'src> MessageParser<'src, '_> {
+    pub fn verify_message_integrity(
+        &self,
+        key: &[u8],
+    ) -> Result<Option<parsed::MessageIntegrity<'src>>, StunError> {
+        let Some(item) = self.integrity_item(
+            Type::MESSAGE_INTEGRITY,
+            &[Type::MESSAGE_INTEGRITY_SHA256, Type::FINGERPRINT],
+        ) else {
+            return Ok(None);
+        };
+        let integrity =
+            parsed::MessageIntegrity::decode(item.attr(), item.value(), self.transaction_id())?;
+        integrity.verify(self.validation_header(), &item, key)?;
+        Ok(Some(integrity))
+    }
+
+    pub fn verify_message_integrity_sha256(
+        &self,
+        key: &[u8],
+    ) -> Result<Option<parsed::MessageIntegritySha256<'src>>, StunError> {
+        let Some(item) = self.integrity_item(Type::MESSAGE_INTEGRITY_SHA256, &[Type::FINGERPRINT])
+        else {
+            return Ok(None);
+        };
+        let integrity = parsed::MessageIntegritySha256::decode(
+            item.attr(),
+            item.value(),
+            self.transaction_id(),
+        )?;
+        integrity.verify(self.validation_header(), &item, key)?;
+        Ok(Some(integrity))
+    }
+
+    // https://datatracker.ietf.org/doc/html/rfc8489#section-14.5
+    // An integrity attribute after one it must precede is ignored whatever the parse options, just
+    // as `AttributeOrdering::Trim` drops it
+    #[inline]
+    fn integrity_item(&self, attr: Type, precedes: &[Type]) -> Option<Item<'src>> {
+        self.iter_raw()
+            .take_while(|item| !precedes.contains(&item.attr()))
+            .find(|item| item.attr() == attr)
+    }
+}
+
+impl<B: Buffer + ?Sized> MessageBuilder<'_, B> {
+    pub fn add_message_integrity(&mut self, key: &[u8]) -> Result<&mut Self, StunError> {
+        const LEN: usize = RawAttribute::TL_LEN + MessageIntegrity::<()>::LEN;
+        const PRECEDES: [Type; 3] =
+            [Type::MESSAGE_INTEGRITY, Type::MESSAGE_INTEGRITY_SHA256, Type::FINGERPRINT];
+        self.ensure_order(Type::MESSAGE_INTEGRITY, &PRECEDES)?;
+        let header = self.validation_header(LEN)?;
+        let integrity = MessageIntegrity::calculate(&header, self.attributes(), key);
+        self.add(&integrity)
+    }
+
+    #[inline]
+    pub fn add_message_integrity_sha256(&mut self, key: &[u8]) -> Result<&mut Self, StunError> {
+        self.add_truncated_message_integrity_sha256(key, MessageIntegritySha256::<()>::MAX_LEN)
+    }
+
+    pub fn add_truncated_message_integrity_sha256(
+        &mut self,
+        key: &[u8],
+        len: usize,
+    ) -> Result<&mut Self, StunError> {
+        message_integrity::validate_len(len)?;
+        self.ensure_order(
+            Type::MESSAGE_INTEGRITY_SHA256,
+            &[Type::MESSAGE_INTEGRITY_SHA256, Type::FINGERPRINT],
+        )?;
+        let header = self.validation_header(RawAttribute::TL_LEN + len)?;
+        let integrity = MessageIntegritySha256::calculate(&header, self.attributes(), key);
+        self.add(&integrity.truncate(len)?)
+    }
+
+    // https://datatracker.ietf.org/doc/html/rfc8489#section-14.5
+    // Only FINGERPRINT may follow MESSAGE-INTEGRITY-SHA256, and only FINGERPRINT or
+    // MESSAGE-INTEGRITY-SHA256 may follow MESSAGE-INTEGRITY
+    fn ensure_order(&mut self, attr: Type, precedes: &[Type]) -> Result<(), StunError> {
+        match precedes.iter().find(|&&other| self.contains(other)) {
+            Some(&other) => Err(IntegrityOrder::new(attr, other).into()),
+            None => Ok(()),
+        }
+    }
+}
+
+#[inline]
+pub(crate) fn mac<M: Mac + KeyInit>(
+    header: &ValidationHeader,
+    attrs_up_to: &[u8],
+    key: &[u8],
+) -> M {
+    let mut mac = <M as KeyInit>::new_from_slice(key).expect("hmac accepts keys of any length");
+    mac.update(header.as_bytes());
+    mac.update(attrs_up_to);
+    mac
+}
+
+new_error!(
+    IntegrityMismatch { attr: Type },
+    ValidationFailed,
+    "{attr} doesn't match the calculated hmac",
+);
+new_error!(
+    IntegrityOrder { attr: Type, other: Type },
+    InvalidParameter,
+    "{attr} can't come after {other}",
+);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        attribute::{rfc8489::Software, UnknownAttribute},
+        error::StunErrorKind,
+        parse::{AttributeOrdering, ParseOptions},
+        test_data::*,
+        Class, Method,
+    };
+    use core::mem::MaybeUninit;
+
+    const PASSWORD: &[u8] = b"VOkJxbRl1RmTxUk/WvJxBt";
+
+    #[test]
+    fn test_verify() {
+        let mut v = [MaybeUninit::uninit(); 32];
+        for parts in &TEST_VECTOR[..3] {
+            let msg = assert_ok!(
+                MessageParser::from_complete_message(parts.message, &mut v),
+                "error parsing raw msg",
+            );
+            let integrity = assert_ok!(msg.verify_message_integrity(PASSWORD), "bad integrity");
+            assert_eq!(integrity.unwrap().value(), parts.attr_parts[parts.attr_parts.len() - 2].v);
+            let err = msg.verify_message_integrity(b"VOkJxbRl1RmTxUk/WvJxBu").unwrap_err();
+            assert_eq!(err.error_kind(), StunErrorKind::ValidationFailed);
+        }
+    }
+
+    #[test]
+    fn test_build() {
+        let (mut v, mut arr) = ([MaybeUninit::uninit(); 32], [0; 256]);
+        for parts in &TEST_VECTOR[..3] {
+            let msg = assert_ok!(
+                MessageParser::from_complete_message(parts.message, &mut v),
+                "error parsing raw msg",
+            );
+            let mut builder = assert_ok!(
+                MessageBuilder::new(msg.class(), msg.method(), *msg.transaction_id(), &mut arr),
+                "error creating builder",
+            );
+            let skip = [Type::MESSAGE_INTEGRITY, Type::FINGERPRINT];
+            for item in msg.iter_raw().filter(|item| !skip.contains(&item.attr())) {
+                let attr = assert_ok!(UnknownAttribute::new(item.attr(), item.value()), "bad attr");
+                assert_ok!(builder.add(&attr), "error adding attribute");
+            }
+            assert_ok!(builder.add_message_integrity(PASSWORD), "error adding integrity");
+            assert_ok!(builder.add_fingerprint(), "error adding fingerprint");
+            let err = builder.add_message_integrity(PASSWORD).map(|_| ()).unwrap_err();
+            assert_eq!(err.error_kind(), StunErrorKind::InvalidParameter);
+            let err = builder.add_message_integrity_sha256(PASSWORD).map(|_| ()).unwrap_err();
+            assert_eq!(err.error_kind(), StunErrorKind::InvalidParameter);
+            assert_ok!(builder.add(&Software::new("ignored")), "error adding trailing attribute");
+            let encoded = builder.finish();
+            let mut v = [MaybeUninit::uninit(); 32];
+            let msg = assert_ok!(
+                MessageParser::from_complete_message(encoded, &mut v),
+                "error parsing built msg",
+            );
+            let integrity = assert_ok!(msg.verify_message_integrity(PASSWORD), "bad integrity");
+            assert!(integrity.is_some());
+        }
+    }
+
+    #[test]
+    fn test_sha256() {
+        let (mut v, mut arr) = ([MaybeUninit::uninit(); 32], [0; 256]);
+        let id = crate::TransactionId::new([7; 12]);
+        for len in [16, 20, 24, 28, 32] {
+            let mut builder = assert_ok!(
+                MessageBuilder::new(Class::Request, Method::BINDING, id, &mut arr),
+                "error creating builder",
+            );
+            assert_ok!(builder.add(&Software::new("stunparse")), "error adding software");
+            assert_ok!(builder.add_message_integrity(PASSWORD), "error adding integrity");
+            assert_ok!(
+                builder.add_truncated_message_integrity_sha256(PASSWORD, len),
+                "error adding integrity sha256",
+            );
+            let err = builder.add_message_integrity(PASSWORD).map(|_| ()).unwrap_err();
+            assert_eq!(err.error_kind(), StunErrorKind::InvalidParameter);
+            let err = builder.add_message_integrity_sha256(PASSWORD).map(|_| ()).unwrap_err();
+            assert_eq!(err.error_kind(), StunErrorKind::InvalidParameter);
+            let encoded = builder.finish();
+            let msg = assert_ok!(
+                MessageParser::from_complete_message(encoded, &mut v),
+                "error parsing built msg",
+            );
+            assert_ok!(msg.verify_message_integrity(PASSWORD), "bad integrity");
+            let integrity =
+                assert_ok!(msg.verify_message_integrity_sha256(PASSWORD), "bad integrity sha256");
+            assert_eq!(integrity.unwrap().value().len(), len);
+            let err = msg.verify_message_integrity_sha256(b"password").unwrap_err();
+            assert_eq!(err.error_kind(), StunErrorKind::ValidationFailed);
+        }
+        let mut builder = assert_ok!(
+            MessageBuilder::new(Class::Request, Method::BINDING, id, &mut arr),
+            "error creating builder",
+        );
+        assert_ok!(builder.add_message_integrity_sha256(PASSWORD), "error adding integrity");
+        let integrity = MessageIntegrity::new(&[0; 20]);
+        assert_ok!(builder.add(&integrity), "error adding integrity");
+        let encoded = builder.finish();
+        let msg = assert_ok!(
+            MessageParser::from_complete_message_with(
+                encoded,
+                &mut v,
+                ParseOptions::new().with_ordering(AttributeOrdering::Trim),
+            ),
+            "error parsing built msg",
+        );
+        assert_eq!(msg.ignored(), 1);
+        assert_ok!(msg.verify_message_integrity_sha256(PASSWORD), "bad integrity sha256");
+        assert!(assert_ok!(msg.verify_message_integrity(PASSWORD), "bad integrity").is_none());
+        let msg = assert_ok!(
+            MessageParser::from_complete_message_with(
+                encoded,
+                &mut v,
+                ParseOptions::new().with_ordering(AttributeOrdering::Keep),
+            ),
+            "error parsing built msg",
+        );
+        assert_eq!(msg.ignored(), 0);
+        assert_ok!(msg.verify_message_integrity_sha256(PASSWORD), "bad integrity sha256");
+        assert!(assert_ok!(msg.verify_message_integrity(PASSWORD), "bad integrity").is_none());
+        let err = MessageParser::from_complete_message_with(
+            encoded,
+            &mut v,
+            ParseOptions::new().with_ordering(AttributeOrdering::Reject),
+        )
+        .map(|_| ())
+        .unwrap_err();
+        assert_eq!(err.error_kind(), StunErrorKind::InvalidParameter);
+        let err = assert_ok!(
+            MessageBuilder::new(Class::Request, Method::BINDING, id, &mut arr),
+            "error creating builder",
+        )
+        .add_truncated_message_integrity_sha256(PASSWORD, 12)
+        .map(|_| ())
+        .unwrap_err();
+        assert_eq!(err.error_kind(), StunErrorKind::InvalidParameter);
+    }
+}
@@ -0,0 +1,180 @@
+use crate::{
+    attribute::{
+        password::Algorithm,
+        rfc8489::{PasswordAlgorithm, Realm, Username},
+        string::is_opaque_string,
+        AsStr,
+    },
+    error::{new_error, StunError},
+};
+use hmac::digest::{Digest, Update};
+use md5::Md5;
+use sha2::Sha256;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum LongTermKey {
+    Md5([u8; 16]),
+    Sha256([u8; 32]),
+}
+
+impl LongTermKey {
+    // https://datatracker.ietf.org/doc/html/rfc8489#section-9.2.2
+    pub fn new<U: AsStr, R: AsStr>(
+        algorithm: Algorithm,
+        username: &Username<U>,
+        realm: &Realm<R>,
+        password: &str,
+    ) -> Result<Self, StunError> {
+        let parts = [username.as_str(), realm.as_str(), password];
+        Ok(match algorithm {
+            Algorithm::MD5 => Self::Md5(digest::<Md5>(&parts)?.into()),
+            Algorithm::SHA256 => Self::Sha256(digest::<Sha256>(&parts)?.into()),
+            Algorithm(codepoint) => return Err(UnsupportedAlgorithm::new(codepoint).into()),
+        })
+    }
+
+    #[inline]
+    pub fn from_password_algorithm<T, U: AsStr, R: AsStr>(
+        algorithm: Option<&PasswordAlgorithm<T>>,
+        username: &Username<U>,
+        realm: &Realm<R>,
+        password: &str,
+    ) -> Result<Self, StunError> {
+        // MD5 is implied when PASSWORD-ALGORITHM is absent
+        let algorithm = algorithm.map_or(Algorithm::MD5, PasswordAlgorithm::algorithm);
+        Self::new(algorithm, username, realm, password)
+    }
+
+    #[inline]
+    pub fn algorithm(&self) -> Algorithm {
+        match self {
+            Self::Md5(_) => Algorithm::MD5,
+            Self::Sha256(_) => Algorithm::SHA256,
+        }
+    }
+
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8] {
+        match self {
+            Self::Md5(key) => key,
+            Self::Sha256(key) => key,
+        }
+    }
+}
+
+impl AsRef<[u8]> for LongTermKey {
+    #[inline]
+    fn as_ref(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+pub(crate) fn digest<D: Digest + Update>(
+    parts: &[&str],
+) -> Result<hmac::digest::Output<D>, StunError> {
+    let mut digest = D::new();
+    for (i, part) in parts.iter().enumerate() {
+        if i != 0 {
+            Update::update(&mut digest, b":");
+        }
+        opaque_string(part, &mut digest)?;
+    }
+    Ok(digest.finalize())
+}
+
+// https://datatracker.ietf.org/doc/html/rfc8265#section-4.2.2
+// The only mapping applied is of non-ASCII spaces to U+0020, the input is assumed to be in NFC.
+fn opaque_string(s: &str, digest: &mut impl Update) -> Result<(), StunError> {
+    if !is_opaque_string(s) {
+        new_error!(
+            NotOpaqueString,
+            InvalidParameter,
+            "credentials do not meet the PRECIS OpaqueString specification",
+        );
+        return Err(NotOpaqueString.into());
+    }
+    let mut rest = s;
+    while let Some(i) = rest.find(is_non_ascii_space) {
+        let (before, space) = rest.split_at(i);
+        digest.update(before.as_bytes());
+        digest.update(b" ");
+        rest = &space[space.chars().next().map_or(0, char::len_utf8)..];
+    }
+    digest.update(rest.as_bytes());
+    Ok(())
+}
+
+#[inline]
+fn is_non_ascii_space(c: char) -> bool {
+    matches!(
+        c,
+        '\u{a0}' | '\u{1680}' | '\u{2000}'..='\u{200a}' | '\u{202f}' | '\u{205f}' | '\u{3000}'
+    )
+}
+
+new_error!(
+    UnsupportedAlgorithm { codepoint: u16 },
+    InvalidParameter,
+    "password algorithm {algorithm} is not supported",
+    algorithm = Algorithm(*codepoint),
+);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{error::StunErrorKind, test_data::*, MessageParser};
+
+    const PASSWORD: &str = "TheMatrIX";
+
+    fn key(algorithm: Algorithm) -> LongTermKey {
+        let (username, realm) = (Username::new(LONG_TERM_USERNAME), Realm::new(LONG_TERM_REALM));
+        assert_ok!(LongTermKey::new(algorithm, &username, &realm, PASSWORD), "error deriving key")
+    }
+
+    #[test]
+    fn test_md5() {
+        let mut v = [core::mem::MaybeUninit::uninit(); 32];
+        let msg = assert_ok!(
+            MessageParser::from_complete_message(&SAMPLE_REQUEST_LONG_TERM_AUTH, &mut v),
+            "error parsing raw msg",
+        );
+        let key = key(Algorithm::MD5);
+        assert_eq!(key.as_bytes().len(), 16);
+        let integrity = assert_ok!(msg.verify_message_integrity(key.as_ref()), "bad integrity");
+        assert!(integrity.is_some());
+    }
+
+    #[test]
+    fn test_sha256() {
+        let mut v = [core::mem::MaybeUninit::uninit(); 32];
+        let msg = assert_ok!(
+            MessageParser::from_complete_message(&SAMPLE_REQUEST_LONG_TERM_AUTH_SHA256, &mut v),
+            "error parsing raw msg",
+        );
+        let algorithm = msg
+            .iter::<crate::attribute::rfc8489::parsed::PasswordAlgorithm>()
+            .find_map(Result::ok)
+            .expect("password algorithm");
+        let (username, realm) = (Username::new(LONG_TERM_USERNAME), Realm::new(LONG_TERM_REALM));
+        let key = assert_ok!(
+            LongTermKey::from_password_algorithm(Some(&algorithm), &username, &realm, PASSWORD),
+            "error deriving key",
+        );
+        assert_eq!(key, self::key(Algorithm::SHA256));
+        let integrity =
+            assert_ok!(msg.verify_message_integrity_sha256(key.as_ref()), "bad integrity");
+        assert!(integrity.is_some());
+    }
+
+    #[test]
+    fn test_opaque_string() {
+        let (username, realm) = (Username::new("user"), Realm::new("realm"));
+        let spaced = LongTermKey::new(Algorithm::MD5, &username, &realm, "pass\u{3000}word");
+        let ascii = LongTermKey::new(Algorithm::MD5, &username, &realm, "pass word");
+        assert_eq!(assert_ok!(spaced, "bad key"), assert_ok!(ascii, "bad key"));
+        let err = LongTermKey::new(Algorithm::MD5, &username, &realm, "\u{7}").unwrap_err();
+        assert_eq!(err.error_kind(), StunErrorKind::InvalidParameter);
+        let err = LongTermKey::new(Algorithm(3), &username, &realm, "password").unwrap_err();
+        assert_eq!(err.error_kind(), StunErrorKind::InvalidParameter);
+    }
+}
@@ -0,0 +1,16 @@
+#[cfg(feature = "alloc")]
+mod client;
+mod integrity;
+mod key;
+mod nonce;
+#[cfg(feature = "alloc")]
+mod server;
+mod userhash;
+
+#[cfg(feature = "alloc")]
+pub use client::ClientAuthenticator;
+pub use key::LongTermKey;
+pub use nonce::{SecurityFeatures, NONCE_COOKIE};
+#[cfg(feature = "alloc")]
+pub use server::{Authenticated, CredentialStore, MemoryStore, Rejection, ServerAuthenticator};
+pub use userhash::UserId;
@@ -0,0 +1,86 @@
+use core::ops::BitOr;
+
+// https://datatracker.ietf.org/doc/html/rfc8489#section-9.2
+// RFC 8489 servers start their nonces with this cookie, followed by the security feature set
+pub const NONCE_COOKIE: &str = "obMatJos2";
+
+const FEATURES_LEN: usize = 4;
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+// https://datatracker.ietf.org/doc/html/rfc8489#section-18.1
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct SecurityFeatures(u32);
+
+impl SecurityFeatures {
+    pub const PASSWORD_ALGORITHMS: Self = Self(1 << 0);
+
+    pub const USERNAME_ANONYMITY: Self = Self(1 << 1);
+
+    #[inline]
+    pub const fn new(bits: u32) -> Self {
+        Self(bits & 0xFF_FFFF)
+    }
+
+    #[inline]
+    pub const fn bits(self) -> u32 {
+        self.0
+    }
+
+    #[inline]
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    // The 24 bits are base64 encoded in the 4 characters following the cookie, nonces without the
+    // cookie come from RFC 5389 servers.
+    pub fn from_nonce(nonce: &str) -> Option<Self> {
+        let encoded = nonce.strip_prefix(NONCE_COOKIE)?.as_bytes().get(..FEATURES_LEN)?;
+        let value = |c| BASE64.iter().position(|&b| b == c).map(|value| value as u32);
+        let bits = encoded.iter().try_fold(0, |bits, &c| Some(bits << 6 | value(c)?))?;
+        Some(Self(bits))
+    }
+
+    // The characters to follow the cookie in a nonce
+    #[inline]
+    pub fn encode(self) -> [u8; FEATURES_LEN] {
+        [18, 12, 6, 0].map(|shift| BASE64[(self.0 >> shift & 0x3F) as usize])
+    }
+}
+
+impl BitOr for SecurityFeatures {
+    type Output = Self;
+
+    #[inline]
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_from_nonce() {
+        let features = SecurityFeatures::from_nonce("obMatJos2AAACf//499k954d6OL34oL9FSTvy64sA");
+        assert_eq!(features, Some(SecurityFeatures::USERNAME_ANONYMITY));
+        let features = SecurityFeatures::from_nonce("obMatJos2AAAD").expect("features");
+        assert!(features.contains(SecurityFeatures::PASSWORD_ALGORITHMS));
+        assert!(features.contains(SecurityFeatures::USERNAME_ANONYMITY));
+        assert_eq!(
+            SecurityFeatures::from_nonce("obMatJos2////"),
+            Some(SecurityFeatures(0xFF_FFFF))
+        );
+        assert_eq!(&features.encode(), b"AAAD");
+        let features = SecurityFeatures::new(0xFB_EF80);
+        assert_eq!(&features.encode(), b"+++A");
+        let mut nonce = *b"obMatJos2....";
+        nonce[NONCE_COOKIE.len()..].copy_from_slice(&features.encode());
+        let nonce = core::str::from_utf8(&nonce).unwrap();
+        assert_eq!(SecurityFeatures::from_nonce(nonce), Some(features));
+        for nonce in ["f//499k954d6OL34oL9FSTvy64sA", "obMatJos2AA", "obMatJos2AA-A"] {
+            assert_eq!(SecurityFeatures::from_nonce(nonce), None);
+        }
+    }
+}
@@ -0,0 +1,492 @@
+use crate::{
+    attribute::{
+        password::Algorithm,
+        rfc8489::{
+            parsed, ErrorCode, Nonce, PasswordAlgorithm, PasswordAlgorithms, Realm, UserHash,
+            Username,
+        },
+        Type,
+    },
+    auth::{LongTermKey, SecurityFeatures, UserId, NONCE_COOKIE},
+    build::{Buffer, MessageBuilder},
+    error::StunError,
+    parse::{AttributeOrdering, MessageParser},
+    time::Instant,
+};
+use alloc::{collections::BTreeMap, string::String};
+use core::{fmt::Write, time::Duration};
+use sha2::{Digest, Sha256};
+
+// In order of preference
+const PASSWORD_ALGORITHMS: [PasswordAlgorithm<&[u8]>; 2] =
+    [PasswordAlgorithm::new(Algorithm::SHA256, &[]), PasswordAlgorithm::new(Algorithm::MD5, &[])];
+
+// Looks up the key of a user in a realm, derived with the requested password algorithm
+pub trait CredentialStore {
+    fn key(&self, user: &UserId, realm: &str, algorithm: Algorithm) -> Option<LongTermKey>;
+}
+
+impl<F: Fn(&UserId, &str, Algorithm) -> Option<LongTermKey>> CredentialStore for F {
+    #[inline]
+    fn key(&self, user: &UserId, realm: &str, algorithm: Algorithm) -> Option<LongTermKey> {
+        self(user, realm, algorithm)
+    }
+}
+
+// Users indexed by their USERHASH, so both USERNAME and USERHASH are looked up directly. Only the
+// derived keys are kept, not the passwords.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryStore {
+    users: BTreeMap<[u8; 32], Keys>,
+}
+
+#[derive(Debug, Clone)]
+struct Keys {
+    realm: String,
+    md5: LongTermKey,
+    sha256: LongTermKey,
+}
+
+impl MemoryStore {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, username: &str, realm: &str, password: &str) -> Result<(), StunError> {
+        let (username, realm) = (Username::new(username), Realm::new(realm));
+        username.validate()?;
+        realm.validate()?;
+        let md5 = LongTermKey::new(Algorithm::MD5, &username, &realm, password)?;
+        let sha256 = LongTermKey::new(Algorithm::SHA256, &username, &realm, password)?;
+        let keys = Keys { realm: realm.as_str().into(), md5, sha256 };
+        self.users.insert(*UserHash::calculate(&username, &realm)?.value(), keys);
+        Ok(())
+    }
+
+    pub fn remove(&mut self, username: &str, realm: &str) -> bool {
+        let user_hash = UserHash::calculate(&Username::new(username), &Realm::new(realm));
+        user_hash.is_ok_and(|user_hash| self.users.remove(user_hash.value()).is_some())
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.users.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.users.is_empty()
+    }
+}
+
+impl CredentialStore for MemoryStore {
+    fn key(&self, user: &UserId, realm: &str, algorithm: Algorithm) -> Option<LongTermKey> {
+        let keys = self.users.get(user.to_user_hash(&Realm::new(realm)).ok()?.value())?;
+        match algorithm {
+            // A USERHASH is looked up regardless of the realm it was calculated with
+            _ if keys.realm != realm => None,
+            Algorithm::MD5 => Some(keys.md5.clone()),
+            Algorithm::SHA256 => Some(keys.sha256.clone()),
+            _ => None,
+        }
+    }
+}
+
+// Why a request was rejected, each answered with its own error response
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Rejection {
+    // Credentials are incomplete or malformed
+    BadRequest,
+    // Credentials are missing or wrong, answered with a challenge
+    Unauthenticated,
+    // The nonce expired, answered with a fresh challenge
+    StaleNonce,
+    // https://datatracker.ietf.org/doc/html/rfc8656#section-7.3
+    // The credentials differ from the ones that created the allocation
+    WrongCredentials,
+}
+
+impl Rejection {
+    #[inline]
+    pub fn error_code(self) -> ErrorCode {
+        match self {
+            Self::BadRequest => ErrorCode::BAD_REQUEST,
+            Self::Unauthenticated => ErrorCode::UNAUTHENTICATED,
+            Self::StaleNonce => ErrorCode::STALE_NONCE,
+            Self::WrongCredentials => ErrorCode::WRONG_CREDENTIALS,
+        }
+    }
+}
+
+// The user of an authenticated request, whose response must be signed with the same key
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Authenticated {
+    user: UserHash<[u8; 32]>,
+    key: LongTermKey,
+    sha256: bool,
+}
+
+impl Authenticated {
+    // The USERHASH identifies the user regardless of whether they sent USERNAME or USERHASH
+    #[inline]
+    pub fn user(&self) -> &[u8; 32] {
+        self.user.value()
+    }
+
+    #[inline]
+    pub fn key(&self) -> &LongTermKey {
+        &self.key
+    }
+
+    // With the integrity attribute the request used
+    pub fn add_message_integrity<B: Buffer + ?Sized>(
+        &self,
+        builder: &mut MessageBuilder<'_, B>,
+    ) -> Result<(), StunError> {
+        match self.sha256 {
+            true => builder.add_message_integrity_sha256(self.key.as_ref())?,
+            false => builder.add_message_integrity(self.key.as_ref())?,
+        };
+        Ok(())
+    }
+}
+
+// https://datatracker.ietf.org/doc/html/rfc8489#section-9.2
+// The server side of the long-term credential mechanism. A single nonce is handed out at a time
+// and replaced once it expires, requests carrying an older one are answered with 438 (Stale
+// Nonce). The secret keys the nonces and should be random.
+pub struct ServerAuthenticator<S, I> {
+    store: S,
+    realm: Realm<String>,
+    secret: [u8; 32],
+    counter: u64,
+    features: SecurityFeatures,
+    nonce: Option<(Nonce<String>, I)>,
+    nonce_lifetime: Duration,
+}
+
+impl<S: CredentialStore, I: Instant> ServerAuthenticator<S, I> {
+    pub const DEFAULT_NONCE_LIFETIME: Duration = Duration::from_secs(3600);
+
+    pub const DEFAULT_FEATURES: SecurityFeatures = SecurityFeatures::new(
+        SecurityFeatures::PASSWORD_ALGORITHMS.bits() | SecurityFeatures::USERNAME_ANONYMITY.bits(),
+    );
+
+    pub fn new(realm: &str, secret: [u8; 32], store: S) -> Result<Self, StunError> {
+        Realm::new(realm).validate()?;
+        Ok(Self {
+            store,
+            realm: Realm::new(realm.into()),
+            secret,
+            counter: 0,
+            features: Self::DEFAULT_FEATURES,
+            nonce: None,
+            nonce_lifetime: Self::DEFAULT_NONCE_LIFETIME,
+        })
+    }
+
+    #[inline]
+    pub fn with_nonce_lifetime(mut self, nonce_lifetime: Duration) -> Self {
+        self.nonce_lifetime = nonce_lifetime;
+        self
+    }
+
+    // Advertised in the nonces, without PASSWORD_ALGORITHMS only MD5 keys are used
+    #[inline]
+    pub fn with_security_features(mut self, features: SecurityFeatures) -> Self {
+        self.features = features;
+        self
+    }
+
+    #[inline]
+    pub fn realm(&self) -> &str {
+        self.realm.as_str()
+    }
+
+    #[inline]
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+
+    #[inline]
+    pub fn store_mut(&mut self) -> &mut S {
+        &mut self.store
+    }
+
+    // https://datatracker.ietf.org/doc/html/rfc8489#section-9.2.4
+    pub fn authenticate(&self, msg: &MessageParser, now: I) -> Result<Authenticated, Rejection> {
+        // Attributes after the integrity are unauthenticated, so they must not have been kept
+        if msg.ordering() == AttributeOrdering::Keep {
+            return Err(Rejection::BadRequest);
+        }
+        let sha256 = msg.iter_raw().any(|item| item.attr() == Type::MESSAGE_INTEGRITY_SHA256);
+        if !sha256 && !msg.iter_raw().any(|item| item.attr() == Type::MESSAGE_INTEGRITY) {
+            return Err(Rejection::Unauthenticated);
+        }
+        let user = msg.user_id();
+        let realm = msg.find::<parsed::Realm>(Type::REALM);
+        let nonce = msg.find::<parsed::Nonce>(Type::NONCE);
+        let (Ok(Some(user)), Ok(Some(realm)), Ok(Some(nonce))) = (user, realm, nonce) else {
+            return Err(Rejection::BadRequest);
+        };
+        let algorithm = password_algorithm(msg, nonce.as_str())?;
+        if !self.is_fresh(nonce.as_str(), now) {
+            return Err(Rejection::StaleNonce);
+        }
+        if realm.as_str() != self.realm.as_str() {
+            return Err(Rejection::Unauthenticated);
+        }
+        let key = self.store.key(&user, self.realm.as_str(), algorithm);
+        let key = key.ok_or(Rejection::Unauthenticated)?;
+        let verified = match sha256 {
+            true => msg.verify_message_integrity_sha256(key.as_ref()).map(|_| ()),
+            false => msg.verify_message_integrity(key.as_ref()).map(|_| ()),
+        };
+        verified.map_err(|_| Rejection::Unauthenticated)?;
+        let user = user.to_user_hash(&self.realm).map_err(|_| Rejection::BadRequest)?;
+        Ok(Authenticated { user, key, sha256 })
+    }
+
+    // Adds the ERROR-CODE, and for challenges the REALM, NONCE and PASSWORD-ALGORITHMS
+    pub fn add_rejection<B: Buffer + ?Sized>(
+        &mut self,
+        rejection: Rejection,
+        now: I,
+        builder: &mut MessageBuilder<'_, B>,
+    ) -> Result<(), StunError> {
+        builder.add(&rejection.error_code())?;
+        if !matches!(rejection, Rejection::Unauthenticated | Rejection::StaleNonce) {
+            return Ok(());
+        }
+        let (realm, nonce) = self.challenge(now);
+        builder.add(realm)?.add(nonce)?;
+        if self.features.contains(SecurityFeatures::PASSWORD_ALGORITHMS) {
+            builder.add(&PasswordAlgorithms::<_, &[u8]>::new(PASSWORD_ALGORITHMS))?;
+        }
+        Ok(())
+    }
+
+    #[inline]
+    fn is_fresh(&self, nonce: &str, now: I) -> bool {
+        self.nonce
+            .as_ref()
+            .is_some_and(|(current, expires)| current.as_str() == nonce && *expires > now)
+    }
+
+    // Issues a new nonce once the current one expires
+    fn challenge(&mut self, now: I) -> (&Realm<String>, &Nonce<String>) {
+        let nonce = match self.nonce.take() {
+            Some(nonce) if nonce.1 > now => nonce,
+            _ => {
+                let mut nonce = String::from(NONCE_COOKIE);
+                nonce.extend(self.features.encode().map(char::from));
+                for b in &self.random()[..12] {
+                    let _ = write!(nonce, "{b:02x}");
+                }
+                (Nonce::new(nonce), now + self.nonce_lifetime)
+            }
+        };
+        (&self.realm, &self.nonce.insert(nonce).0)
+    }
+
+    #[inline]
+    fn random(&mut self) -> [u8; 32] {
+        self.counter += 1;
+        Sha256::new()
+            .chain_update(b"nonce")
+            .chain_update(self.secret)
+            .chain_update(self.counter.to_be_bytes())
+            .finalize()
+            .into()
+    }
+}
+
+// MD5 unless the nonce advertises password algorithms and the request echoes the offered ones
+// along with its choice
+fn password_algorithm(msg: &MessageParser, nonce: &str) -> Result<Algorithm, Rejection> {
+    let offered = msg.find::<parsed::PasswordAlgorithms>(Type::PASSWORD_ALGORITHMS);
+    let chosen = msg.find::<parsed::PasswordAlgorithm>(Type::PASSWORD_ALGORITHM);
+    let (Ok(offered), Ok(chosen)) = (offered, chosen) else {
+        return Err(Rejection::BadRequest);
+    };
+    let features = SecurityFeatures::from_nonce(nonce).unwrap_or_default();
+    match (offered, chosen) {
+        (None, None) => Ok(Algorithm::MD5),
+        (Some(offered), Some(chosen))
+            if features.contains(SecurityFeatures::PASSWORD_ALGORITHMS)
+                && offered.iter().eq(PASSWORD_ALGORITHMS)
+                && PASSWORD_ALGORITHMS.contains(&chosen) =>
+        {
+            Ok(chosen.algorithm())
+        }
+        _ => Err(Rejection::BadRequest),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        auth::ClientAuthenticator, parse::ParseOptions, test_data::assert_ok, Class, Method,
+        TransactionId,
+    };
+    use core::mem::MaybeUninit;
+
+    const REALM: &str = "example.org";
+    const PASSWORD: &str = "password";
+
+    type Server = ServerAuthenticator<MemoryStore, Duration>;
+
+    fn server() -> Server {
+        let mut store = MemoryStore::new();
+        assert_ok!(store.insert("user", REALM, PASSWORD), "bad credentials");
+        let server = assert_ok!(ServerAuthenticator::new(REALM, [3; 32], store), "bad realm");
+        server.with_nonce_lifetime(Duration::from_secs(60))
+    }
+
+    // Builds a binding request with the client's credentials, and whatever else `add` adds
+    fn request<'a>(
+        buf: &'a mut [u8],
+        client: &ClientAuthenticator,
+        add: impl FnOnce(&mut MessageBuilder<'_, [u8]>),
+    ) -> &'a mut [u8] {
+        let id = TransactionId::new([1; 12]);
+        let mut builder = MessageBuilder::new(Class::Request, Method::BINDING, id, buf).unwrap();
+        add(&mut builder);
+        client.add_credentials(&mut builder).unwrap();
+        builder.finish()
+    }
+
+    fn authenticate(server: &Server, src: &[u8], now: u64) -> Result<Authenticated, Rejection> {
+        let mut v = [MaybeUninit::uninit(); 16];
+        let msg = assert_ok!(MessageParser::from_complete_message(src, &mut v), "bad request");
+        server.authenticate(&msg, Duration::from_secs(now))
+    }
+
+    // Lets the client handle the rejection's error response, returning whether it retries
+    fn reject(
+        server: &mut Server,
+        client: &mut ClientAuthenticator,
+        rejection: Rejection,
+        now: u64,
+    ) -> bool {
+        let (mut buf, mut v) = ([0; 256], [MaybeUninit::uninit(); 16]);
+        let id = TransactionId::new([1; 12]);
+        let mut builder =
+            MessageBuilder::new(Class::ErrorResponse, Method::BINDING, id, &mut buf[..]).unwrap();
+        assert_ok!(
+            server.add_rejection(rejection, Duration::from_secs(now), &mut builder),
+            "error rejecting"
+        );
+        let encoded = builder.finish();
+        let msg = assert_ok!(MessageParser::from_complete_message(encoded, &mut v), "bad response");
+        let code = msg.find::<parsed::ErrorCode>(Type::ERROR_CODE).unwrap().expect("error code");
+        assert_eq!(code.code(), rejection.error_code().code());
+        assert_ok!(client.handle_error(&msg, false), "error handling rejection")
+    }
+
+    #[test]
+    fn test_authenticate() {
+        let (mut server, mut buf) = (server(), [0; 512]);
+        let mut client = ClientAuthenticator::new("user", PASSWORD).unwrap();
+        let src = request(&mut buf, &client, |_| {});
+        assert_eq!(authenticate(&server, src, 0), Err(Rejection::Unauthenticated));
+        assert!(reject(&mut server, &mut client, Rejection::Unauthenticated, 0));
+        let nonce = String::from(client.nonce().expect("nonce"));
+        assert!(nonce.starts_with("obMatJos2AAAD"));
+
+        // SHA-256 key, USERHASH and MESSAGE-INTEGRITY-SHA256
+        let src = request(&mut buf, &client, |_| {});
+        let authenticated = authenticate(&server, src, 0).expect("unauthenticated");
+        let user_hash = UserHash::calculate(&Username::new("user"), &Realm::new(REALM)).unwrap();
+        assert_eq!(authenticated.user(), user_hash.value());
+        assert_eq!(authenticated.key().algorithm(), Algorithm::SHA256);
+        let (mut res, mut v) = ([0; 128], [MaybeUninit::uninit(); 4]);
+        let id = TransactionId::new([1; 12]);
+        let mut builder =
+            MessageBuilder::new(Class::SuccessResponse, Method::BINDING, id, &mut res[..]).unwrap();
+        assert_ok!(authenticated.add_message_integrity(&mut builder), "error signing");
+        let encoded = builder.finish();
+        assert_ok!(
+            client.verify(&MessageParser::from_complete_message(encoded, &mut v).unwrap()),
+            "bad mac"
+        );
+
+        let mut wrong = ClientAuthenticator::new("user", "wrong").unwrap();
+        assert!(reject(&mut server, &mut wrong, Rejection::Unauthenticated, 0));
+        let src = request(&mut buf, &wrong, |_| {});
+        assert_eq!(authenticate(&server, src, 0), Err(Rejection::Unauthenticated));
+        let src = request(&mut buf, &client, |builder| {
+            builder.add(&PasswordAlgorithm::new(Algorithm::new(3), &[][..])).unwrap();
+        });
+        assert_eq!(authenticate(&server, src, 0), Err(Rejection::BadRequest));
+
+        // the nonce expires, and a fresh one is handed out
+        let src = request(&mut buf, &client, |_| {});
+        assert_eq!(authenticate(&server, src, 60), Err(Rejection::StaleNonce));
+        assert!(reject(&mut server, &mut client, Rejection::StaleNonce, 60));
+        assert_ne!(client.nonce(), Some(nonce.as_str()));
+        let src = request(&mut buf, &client, |_| {});
+        authenticate(&server, src, 60).expect("unauthenticated");
+        assert!(!reject(&mut server, &mut client, Rejection::WrongCredentials, 60));
+    }
+
+    #[test]
+    fn test_ordering() {
+        let (mut server, mut buf) = (server(), [0; 512]);
+        let mut client = ClientAuthenticator::new("user", PASSWORD).unwrap();
+        assert!(reject(&mut server, &mut client, Rejection::Unauthenticated, 0));
+        let id = TransactionId::new([1; 12]);
+        let mut builder =
+            MessageBuilder::new(Class::Request, Method::BINDING, id, &mut buf[..]).unwrap();
+        client.add_credentials(&mut builder).unwrap();
+        builder.add(&Username::new("other")).unwrap();
+        let src = builder.finish();
+        // the trailing USERNAME is trimmed by the default options
+        let authenticated = authenticate(&server, src, 0).expect("unauthenticated");
+        let user_hash = UserHash::calculate(&Username::new("user"), &Realm::new(REALM)).unwrap();
+        assert_eq!(authenticated.user(), user_hash.value());
+        let mut v = [MaybeUninit::uninit(); 16];
+        let options = ParseOptions::new().with_ordering(AttributeOrdering::Keep);
+        let msg =
+            assert_ok!(MessageParser::from_complete_message_with(src, &mut v, options), "bad");
+        assert_eq!(server.authenticate(&msg, Duration::ZERO), Err(Rejection::BadRequest));
+    }
+
+    #[test]
+    fn test_rfc5389() {
+        let mut server = server().with_security_features(SecurityFeatures::default());
+        let mut client = ClientAuthenticator::new("user", PASSWORD).unwrap();
+        assert!(reject(&mut server, &mut client, Rejection::Unauthenticated, 0));
+        let mut buf = [0; 256];
+        let src = request(&mut buf, &client, |_| {});
+        let authenticated = authenticate(&server, src, 0).expect("unauthenticated");
+        assert_eq!(authenticated.key().algorithm(), Algorithm::MD5);
+    }
+
+    #[test]
+    fn test_memory_store() {
+        let mut store = MemoryStore::new();
+        assert_ok!(store.insert("user", REALM, PASSWORD), "bad credentials");
+        assert!(store.insert("user\u{7}", REALM, PASSWORD).is_err());
+        let (username, realm) = (Username::new("user"), Realm::new(REALM));
+        let user_hash = UserHash::calculate(&username, &realm).unwrap();
+        let ids = [
+            UserId::Username(username.validate().unwrap()),
+            UserId::UserHash(UserHash::new(user_hash.value())),
+        ];
+        for id in &ids {
+            for algorithm in [Algorithm::MD5, Algorithm::SHA256] {
+                let key = LongTermKey::new(algorithm, &username, &realm, PASSWORD).unwrap();
+                assert_eq!(store.key(id, REALM, algorithm), Some(key));
+            }
+            assert_eq!(store.key(id, REALM, Algorithm::new(3)), None);
+            assert_eq!(store.key(id, "example.com", Algorithm::MD5), None);
+        }
+        assert_eq!(store.len(), 1);
+        assert!(!store.remove("user", "example.com"));
+        assert!(store.remove("user", REALM));
+        assert!(store.is_empty());
+    }
+}
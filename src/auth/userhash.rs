@@ -0,0 +1,94 @@
+use crate::{
+    attribute::{
+        rfc8489::{parsed, Realm, UserHash, Username},
+        AsStr, DecodeAttribute, Type,
+    },
+    error::StunError,
+    parse::MessageParser,
+};
+use sha2::Sha256;
+
+impl UserHash<[u8; 32]> {
+    // https://datatracker.ietf.org/doc/html/rfc8489#section-14.4
+    #[inline]
+    pub fn calculate<U: AsStr, R: AsStr>(
+        username: &Username<U>,
+        realm: &Realm<R>,
+    ) -> Result<Self, StunError> {
+        let hash = super::key::digest::<Sha256>(&[username.as_str(), realm.as_str()])?;
+        Ok(Self::new(hash.into()))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum UserId<'a> {
+    Username(parsed::Username<'a>),
+    UserHash(parsed::UserHash<'a>),
+}
+
+impl UserId<'_> {
+    pub fn matches<U: AsStr, R: AsStr>(
+        &self,
+        username: &Username<U>,
+        realm: &Realm<R>,
+    ) -> Result<bool, StunError> {
+        Ok(match self {
+            Self::Username(name) => name.as_str() == username.as_str(),
+            Self::UserHash(hash) => UserHash::calculate(username, realm)?.value() == hash.value(),
+        })
+    }
+
+    // A common key for credential lookups regardless of how the user identified themselves
+    pub fn to_user_hash<R: AsStr>(
+        &self,
+        realm: &Realm<R>,
+    ) -> Result<UserHash<[u8; 32]>, StunError> {
+        match self {
+            Self::Username(name) => UserHash::calculate(name, realm),
+            Self::UserHash(hash) => Ok(UserHash::new(*hash.value())),
+        }
+    }
+}
+
+impl<'src> MessageParser<'src, '_> {
+    pub fn user_id(&self) -> Result<Option<UserId<'src>>, StunError> {
+        let id = self.transaction_id();
+        for item in self.iter_raw() {
+            return Ok(Some(match item.attr() {
+                Type::USERNAME => {
+                    UserId::Username(parsed::Username::decode(item.attr(), item.value(), id)?)
+                }
+                Type::USERHASH => {
+                    UserId::UserHash(parsed::UserHash::decode(item.attr(), item.value(), id)?)
+                }
+                _ => continue,
+            }));
+        }
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_data::*;
+
+    #[test]
+    fn test_user_hash() {
+        let (username, realm) = (Username::new(LONG_TERM_USERNAME), Realm::new(LONG_TERM_REALM));
+        let hash = assert_ok!(UserHash::calculate(&username, &realm), "error hashing user");
+        let mut v = [core::mem::MaybeUninit::uninit(); 32];
+        for (parts, hashed) in [(&TEST_VECTOR[3], false), (&TEST_VECTOR[4], true)] {
+            let msg = assert_ok!(
+                MessageParser::from_complete_message(parts.message, &mut v),
+                "error parsing raw msg",
+            );
+            let user_id = assert_ok!(msg.user_id(), "error decoding user").expect("user id");
+            assert_eq!(matches!(user_id, UserId::UserHash(_)), hashed);
+            assert!(assert_ok!(user_id.matches(&username, &realm), "error matching user"));
+            assert_eq!(assert_ok!(user_id.to_user_hash(&realm), "error hashing user"), hash);
+            let other = Username::new("other");
+            assert!(!assert_ok!(user_id.matches(&other, &realm), "error matching user"));
+        }
+    }
+}
@@ -0,0 +1,62 @@
+pub trait Buffer {
+    fn reserve(&mut self, off: usize, additional: usize) -> &mut [u8];
+
+    fn finish(&mut self, len: usize) -> &mut [u8];
+}
+
+impl Buffer for [u8] {
+    #[inline]
+    fn reserve(&mut self, off: usize, _: usize) -> &mut [u8] {
+        &mut self[off..]
+    }
+
+    #[inline]
+    fn finish(&mut self, len: usize) -> &mut [u8] {
+        &mut self[..len]
+    }
+}
+
+impl<const N: usize> Buffer for [u8; N] {
+    #[inline]
+    fn reserve(&mut self, off: usize, additional: usize) -> &mut [u8] {
+        self.as_mut_slice().reserve(off, additional)
+    }
+
+    #[inline]
+    fn finish(&mut self, len: usize) -> &mut [u8] {
+        self.as_mut_slice().finish(len)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl Buffer for alloc::vec::Vec<u8> {
+    fn reserve(&mut self, off: usize, additional: usize) -> &mut [u8] {
+        let len = off + additional;
+        if self.len() < len {
+            self.resize(len, 0);
+        }
+        &mut self[off..]
+    }
+
+    fn finish(&mut self, len: usize) -> &mut [u8] {
+        self.truncate(len);
+        self.as_mut_slice()
+    }
+}
+
+#[cfg(feature = "heapless")]
+impl<const N: usize> Buffer for heapless::Vec<u8, N> {
+    fn reserve(&mut self, off: usize, additional: usize) -> &mut [u8] {
+        let len = N.min(off + additional);
+        if self.len() < len {
+            // can't fail, len is at most N
+            let _ = self.resize(len, 0);
+        }
+        &mut self[off..]
+    }
+
+    fn finish(&mut self, len: usize) -> &mut [u8] {
+        self.truncate(len);
+        self.as_mut_slice()
+    }
+}
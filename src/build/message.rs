@@ -0,0 +1,231 @@
+use crate::{
+    attribute::{rfc8489::Fingerprint, EncodeAttribute},
+    build::Buffer,
+    error::{new_error, StunError},
+    header::{Class, Header, Method, TransactionId},
+    parse::{RawAttribute, ValidationHeader},
+    util,
+};
+
+pub struct MessageBuilder<'b, B: Buffer + ?Sized> {
+    header: Header,
+    buf: &'b mut B,
+}
+
+impl<'b, B: Buffer + ?Sized> MessageBuilder<'b, B> {
+    // The length in the header must be a multiple of 4, and fit in 2 bytes.
+    pub const MAX_LEN: usize = u16::MAX as usize - RawAttribute::MAX_PADDING_LEN;
+
+    #[inline]
+    pub fn new(
+        class: Class,
+        method: Method,
+        transaction_id: TransactionId,
+        buf: &'b mut B,
+    ) -> Result<Self, StunError> {
+        Self::from_header(Header::new(class, method, 0, transaction_id), buf)
+    }
+
+    pub fn from_header(mut header: Header, buf: &'b mut B) -> Result<Self, StunError> {
+        let len = buf.reserve(0, Header::LEN).len();
+        if len < Header::LEN {
+            new_error!(
+                HeaderTooBig { len: usize },
+                BufferTooSmall,
+                "dst of len {len} can't fit a header of len 20",
+            );
+            return Err(HeaderTooBig::new(len).into());
+        }
+        header.length = 0;
+        Ok(Self { header, buf })
+    }
+
+    pub fn add<A: EncodeAttribute + ?Sized>(&mut self, attr: &A) -> Result<&mut Self, StunError> {
+        let (len, encoded_len) = (self.header.length as usize, attr.encoded_len());
+        self.ensure_len(encoded_len)?;
+        let dst = self.buf.reserve(Header::LEN + len, encoded_len);
+        let dst_len = dst.len();
+        let rem = attr.encode(dst, &self.header.transaction_id)?.len();
+        self.header.length = (len + dst_len - rem) as u16;
+        Ok(self)
+    }
+
+    pub fn add_fingerprint(&mut self) -> Result<&mut Self, StunError> {
+        const LEN: usize = RawAttribute::TL_LEN + core::mem::size_of::<u32>();
+        let header = self.validation_header(LEN)?;
+        let fingerprint = Fingerprint::calculate(&header, self.attributes());
+        self.add(&fingerprint)
+    }
+
+    #[inline]
+    pub(crate) fn validation_header(
+        &self,
+        encoded_len: usize,
+    ) -> Result<ValidationHeader, StunError> {
+        let len = self.ensure_len(encoded_len)?;
+        let mut header = ValidationHeader::new(&self.header);
+        header.set_len(len as u16);
+        Ok(header)
+    }
+
+    #[inline]
+    pub(crate) fn attributes(&mut self) -> &[u8] {
+        let len = self.header.length as usize;
+        &self.buf.reserve(Header::LEN, len)[..len]
+    }
+
+    #[cfg(feature = "auth")]
+    pub(crate) fn contains(&mut self, attr: crate::attribute::Type) -> bool {
+        let mut attributes = self.attributes();
+        while attributes.len() >= RawAttribute::TL_LEN {
+            let (tl, rest) = util::split_array_ref(attributes);
+            let (t, len) = RawAttribute::decode_type_length(tl);
+            if t == attr {
+                return true;
+            }
+            attributes = rest.get(RawAttribute::padded_len(len) as usize..).unwrap_or_default();
+        }
+        false
+    }
+
+    #[inline]
+    fn ensure_len(&self, encoded_len: usize) -> Result<usize, StunError> {
+        let len = self.header.length as usize + encoded_len;
+        if len <= Self::MAX_LEN {
+            return Ok(len);
+        }
+        new_error!(
+            MessageTooLong { len: usize },
+            ValueTooLong,
+            "the attributes would be {len} bytes long, larger than the max allowed (65532)",
+        );
+        Err(MessageTooLong::new(len).into())
+    }
+
+    #[inline]
+    pub fn class(&self) -> Class {
+        self.header.class
+    }
+
+    #[inline]
+    pub fn method(&self) -> Method {
+        self.header.method
+    }
+
+    #[inline]
+    pub fn transaction_id(&self) -> &TransactionId {
+        &self.header.transaction_id
+    }
+
+    #[inline]
+    pub fn length(&self) -> u16 {
+        self.header.length
+    }
+
+    pub fn finish(self) -> &'b mut [u8] {
+        let Self { header, buf } = self;
+        let msg = buf.finish(Header::LEN + header.length as usize);
+        header.encode(util::split_array_mut(msg).0);
+        msg
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        attribute::{
+            rfc8489::{Software, XorMappedAddress},
+            UnknownAttribute,
+        },
+        error::StunErrorKind,
+        test_data::*,
+        MessageParser,
+    };
+    use core::mem::MaybeUninit;
+
+    fn rebuild<'b, B: Buffer + ?Sized>(msg: &MessageParser, buf: &'b mut B) -> &'b mut [u8] {
+        let mut builder = assert_ok!(
+            MessageBuilder::new(msg.class(), msg.method(), *msg.transaction_id(), buf),
+            "error creating builder",
+        );
+        for item in msg.iter_raw() {
+            let attr = assert_ok!(UnknownAttribute::new(item.attr(), item.value()), "bad attr");
+            assert_ok!(builder.add(&attr), "error adding attribute");
+        }
+        builder.finish()
+    }
+
+    #[test]
+    fn test_build() {
+        let mut v = [MaybeUninit::uninit(); 32];
+        let mut arr = [0; 256];
+        // the rest of the vectors pad with spaces instead of zeroes
+        for parts in &TEST_VECTOR[3..] {
+            let msg = assert_ok!(
+                MessageParser::from_complete_message(parts.message, &mut v),
+                "error parsing raw msg",
+            );
+            assert_eq!(rebuild(&msg, &mut arr), parts.message);
+            #[cfg(feature = "alloc")]
+            assert_eq!(rebuild(&msg, &mut alloc::vec![0xFF; 3]), parts.message);
+            #[cfg(feature = "heapless")]
+            assert_eq!(rebuild(&msg, &mut heapless::Vec::<_, 256>::new()), parts.message);
+        }
+    }
+
+    #[test]
+    fn test_length() {
+        let mut arr = [0; 56];
+        let id = TransactionId::new([1; 12]);
+        let software = Software::new("stunparse");
+        let addr = XorMappedAddress::new("192.0.2.1:32853".parse().unwrap());
+        let mut builder = assert_ok!(
+            MessageBuilder::new(Class::SuccessResponse, Method::BINDING, id, arr.as_mut_slice()),
+            "error creating builder",
+        );
+        assert_ok!(builder.add(&software), "error adding software");
+        assert_ok!(builder.add(&addr), "error adding address");
+        assert_eq!(builder.length() as usize, software.encoded_len() + addr.encoded_len());
+        let err = builder.add(&software).map(|_| ()).unwrap_err();
+        assert_eq!(err.error_kind(), StunErrorKind::BufferTooSmall);
+        let msg = builder.finish();
+        let header = assert_ok!(Header::decode(util::split_array_ref(msg).0), "bad header");
+        assert_eq!(header.length() as usize, msg.len() - Header::LEN);
+        assert_eq!(header.class(), Class::SuccessResponse);
+        assert_eq!(header.transaction_id(), id);
+        let err = MessageBuilder::new(Class::Request, Method::BINDING, id, &mut [0; 19])
+            .map(|_| ())
+            .unwrap_err();
+        assert_eq!(err.error_kind(), StunErrorKind::BufferTooSmall);
+    }
+
+    #[test]
+    fn test_fingerprint() {
+        let mut v = [MaybeUninit::uninit(); 32];
+        let mut arr = [0; 256];
+        for parts in TEST_VECTOR {
+            let msg = assert_ok!(
+                MessageParser::from_complete_message(parts.message, &mut v),
+                "error parsing raw msg",
+            );
+            let mut builder = assert_ok!(
+                MessageBuilder::new(msg.class(), msg.method(), *msg.transaction_id(), &mut arr),
+                "error creating builder",
+            );
+            for item in msg.iter_raw().filter(|item| item.attr() != Fingerprint::TYPE) {
+                let attr = assert_ok!(UnknownAttribute::new(item.attr(), item.value()), "bad attr");
+                assert_ok!(builder.add(&attr), "error adding attribute");
+            }
+            assert_ok!(builder.add_fingerprint(), "error adding fingerprint");
+            let encoded = builder.finish();
+            let mut v = [MaybeUninit::uninit(); 32];
+            let msg = assert_ok!(
+                MessageParser::from_complete_message(encoded, &mut v),
+                "error parsing built msg",
+            );
+            let fingerprint = assert_ok!(msg.verify_fingerprint(), "error verifying fingerprint");
+            assert!(fingerprint.is_some());
+        }
+    }
+}
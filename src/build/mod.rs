@@ -0,0 +1,5 @@
+mod buffer;
+mod message;
+
+pub use buffer::Buffer;
+pub use message::MessageBuilder;
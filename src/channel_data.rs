@@ -0,0 +1,198 @@
+use crate::{
+    error::{new_error, StunError},
+    parse::RawAttribute,
+    util,
+};
+
+const HEADER_LEN: usize = 4;
+
+// https://datatracker.ietf.org/doc/html/rfc8656#section-12.4
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ChannelData<'a> {
+    channel: u16,
+    data: &'a [u8],
+}
+
+// Over streams the message is padded to a multiple of 4, over datagrams the padding is optional.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Framing {
+    Datagram,
+    Stream,
+}
+
+impl<'a> ChannelData<'a> {
+    pub const HEADER_LEN: usize = HEADER_LEN;
+
+    pub const MIN_CHANNEL: u16 = 0x4000;
+
+    pub const MAX_CHANNEL: u16 = 0x4FFF;
+
+    pub const MAX_DATA_LEN: usize = u16::MAX as usize;
+
+    pub fn new(channel: u16, data: &'a [u8]) -> Result<Self, StunError> {
+        validate_channel(channel)?;
+        if data.len() > Self::MAX_DATA_LEN {
+            new_error!(
+                DataTooLong { len: usize },
+                ValueTooLong,
+                "channel data of {len} bytes doesn't fit in a 2 byte length",
+            );
+            return Err(DataTooLong::new(data.len()).into());
+        }
+        Ok(Self { channel, data })
+    }
+
+    #[inline]
+    pub const fn is_valid_channel(channel: u16) -> bool {
+        channel >= Self::MIN_CHANNEL && channel <= Self::MAX_CHANNEL
+    }
+
+    // The datagram must hold the message and at most its padding, the stream frame must hold
+    // exactly the padded message.
+    pub fn decode(src: &'a [u8], framing: Framing) -> Result<Self, StunError> {
+        if src.len() < Self::HEADER_LEN {
+            new_error!(
+                HeaderTooBig { len: usize },
+                BufferTooSmall,
+                "src of len {len} can't fit a channel data header of len 4",
+            );
+            return Err(HeaderTooBig::new(src.len()).into());
+        }
+        let (header, data) = util::split_array_ref(src);
+        let (channel, len) = Self::decode_header(header);
+        validate_channel(channel)?;
+        let (len, padded_len) = (len as usize, padded_len(len as usize));
+        let min = match framing {
+            Framing::Datagram => len,
+            Framing::Stream => padded_len,
+        };
+        if !(min..=padded_len).contains(&data.len()) {
+            new_error!(
+                LenMismatch { len: u16, actual: u16 },
+                InvalidParameter,
+                "channel data of length {len} doesn't match the {actual} bytes remaining",
+            );
+            return Err(LenMismatch::new(len as u16, data.len() as u16).into());
+        }
+        Ok(Self { channel, data: &data[..len] })
+    }
+
+    #[inline]
+    pub fn decode_header(src: &[u8; HEADER_LEN]) -> (u16, u16) {
+        let (channel, len) = util::split_array_exact_ref(src);
+        (u16::from_be_bytes(*channel), u16::from_be_bytes(*len))
+    }
+
+    #[inline]
+    pub fn encode_header(channel: u16, len: u16, dst: &mut [u8; HEADER_LEN]) {
+        let (c, l) = util::split_array_exact_mut(dst);
+        *c = channel.to_be_bytes();
+        *l = len.to_be_bytes();
+    }
+
+    #[inline]
+    pub fn encoded_len(&self, framing: Framing) -> usize {
+        HEADER_LEN
+            + match framing {
+                Framing::Datagram => self.data.len(),
+                Framing::Stream => padded_len(self.data.len()),
+            }
+    }
+
+    pub fn encode<'b>(
+        &self,
+        dst: &'b mut [u8],
+        framing: Framing,
+    ) -> Result<&'b mut [u8], StunError> {
+        let encoded_len = self.encoded_len(framing);
+        if dst.len() < encoded_len {
+            new_error!(
+                BufferTooSmall { required: u32, actual: u32 },
+                BufferTooSmall,
+                "channel data requires a buffer of {required} bytes, buffer of length {actual} provided",
+            );
+            return Err(BufferTooSmall::new(encoded_len as u32, dst.len() as u32).into());
+        }
+        let (message, rest) = dst.split_at_mut(encoded_len);
+        let (header, value) = util::split_array_mut(message);
+        Self::encode_header(self.channel, self.data.len() as u16, header);
+        let (data, pad) = value.split_at_mut(self.data.len());
+        data.copy_from_slice(self.data);
+        pad.fill(0);
+        Ok(rest)
+    }
+
+    #[inline]
+    pub fn channel(&self) -> u16 {
+        self.channel
+    }
+
+    #[inline]
+    pub fn data(&self) -> &'a [u8] {
+        self.data
+    }
+}
+
+// usize, as the max length padded overflows u16
+#[inline]
+pub(crate) const fn padded_len(len: usize) -> usize {
+    (len + RawAttribute::MAX_PADDING_LEN) & !RawAttribute::MAX_PADDING_LEN
+}
+
+#[inline]
+pub(crate) fn validate_channel(channel: u16) -> Result<(), StunError> {
+    if ChannelData::is_valid_channel(channel) {
+        return Ok(());
+    }
+    new_error!(
+        InvalidChannel { channel: u16 },
+        InvalidParameter,
+        "channel number {channel:#06X} is outside of the 0x4000-0x4FFF range",
+    );
+    Err(InvalidChannel::new(channel).into())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{error::StunErrorKind, test_data::assert_ok};
+
+    #[test]
+    fn test_encode_decode() {
+        let mut buf = [0xFF; 16];
+        let data = ChannelData::new(0x4001, b"hello").unwrap();
+        assert_eq!(data.encoded_len(Framing::Datagram), 9);
+        assert_eq!(data.encoded_len(Framing::Stream), 12);
+        let rest = assert_ok!(data.encode(&mut buf, Framing::Stream), "error encoding").len();
+        assert_eq!(rest, 4);
+        assert_eq!(&buf[..12], b"\x40\x01\x00\x05hello\0\0\0");
+        for framing in [Framing::Datagram, Framing::Stream] {
+            let decoded = assert_ok!(ChannelData::decode(&buf[..12], framing), "error decoding");
+            assert_eq!(decoded, data);
+        }
+        let decoded = assert_ok!(ChannelData::decode(&buf[..9], Framing::Datagram), "bad data");
+        assert_eq!(decoded.data(), b"hello");
+        for (len, framing) in
+            [(8, Framing::Datagram), (13, Framing::Datagram), (9, Framing::Stream)]
+        {
+            let err = ChannelData::decode(&buf[..len], framing).unwrap_err();
+            assert_eq!(err.error_kind(), StunErrorKind::InvalidParameter);
+        }
+        let err = data.encode(&mut buf[..11], Framing::Stream).unwrap_err();
+        assert_eq!(err.error_kind(), StunErrorKind::BufferTooSmall);
+    }
+
+    #[test]
+    fn test_channel() {
+        assert!(ChannelData::new(0x4000, &[]).is_ok());
+        assert!(ChannelData::new(0x4FFF, &[]).is_ok());
+        for channel in [0x3FFF, 0x5000, 0x7FFF] {
+            let err = ChannelData::new(channel, &[]).unwrap_err();
+            assert_eq!(err.error_kind(), StunErrorKind::InvalidParameter);
+        }
+        let err = ChannelData::decode(&[0x50, 0, 0, 0], Framing::Datagram).unwrap_err();
+        assert_eq!(err.error_kind(), StunErrorKind::InvalidParameter);
+        let err = ChannelData::decode(&[0x40, 0], Framing::Datagram).unwrap_err();
+        assert_eq!(err.error_kind(), StunErrorKind::BufferTooSmall);
+    }
+}
@@ -0,0 +1,11 @@
+#[cfg(feature = "alloc")]
+mod table;
+mod transaction;
+#[cfg(all(feature = "alloc", feature = "auth"))]
+mod turn;
+
+#[cfg(feature = "alloc")]
+pub use table::TransactionTable;
+pub use transaction::{ClientTransaction, Outcome, TransactionConfig, Transport};
+#[cfg(all(feature = "alloc", feature = "auth"))]
+pub use turn::{TurnClient, TurnEvent};
@@ -0,0 +1,238 @@
+use crate::{
+    client::{ClientTransaction, Outcome},
+    error::{new_error, StunError},
+    header::TransactionId,
+    net::SocketAddr,
+    time::Instant,
+};
+use alloc::collections::BTreeMap;
+
+#[derive(Debug, Clone)]
+struct Entry<M, I> {
+    destination: SocketAddr,
+    transaction: ClientTransaction<M, I>,
+}
+
+// Many outstanding client transactions, driven the same way as a single one
+#[derive(Debug, Clone)]
+pub struct TransactionTable<M, I> {
+    transactions: BTreeMap<TransactionId, Entry<M, I>>,
+    outstanding: BTreeMap<SocketAddr, usize>,
+    max_per_destination: usize,
+    unmatched: u64,
+}
+
+impl<M: AsRef<[u8]>, I: Instant> TransactionTable<M, I> {
+    // https://datatracker.ietf.org/doc/html/rfc8489#section-6.2.1
+    pub const DEFAULT_MAX_PER_DESTINATION: usize = 10;
+
+    #[inline]
+    pub fn new(max_per_destination: usize) -> Self {
+        Self {
+            transactions: BTreeMap::new(),
+            outstanding: BTreeMap::new(),
+            max_per_destination,
+            unmatched: 0,
+        }
+    }
+
+    pub fn insert(
+        &mut self,
+        destination: SocketAddr,
+        transaction: ClientTransaction<M, I>,
+    ) -> Result<(), StunError> {
+        let outstanding = self.outstanding(&destination);
+        if outstanding >= self.max_per_destination {
+            new_error!(
+                TooManyOutstanding { outstanding: u16 },
+                InvalidParameter,
+                "{outstanding} transactions are already outstanding to this destination",
+            );
+            return Err(TooManyOutstanding::new(outstanding.try_into().unwrap_or(u16::MAX)).into());
+        }
+        let id = *transaction.transaction_id();
+        if self.transactions.contains_key(&id) {
+            new_error!(
+                DuplicateTransaction,
+                InvalidParameter,
+                "the transaction id is already in use"
+            );
+            return Err(DuplicateTransaction.into());
+        }
+        if !transaction.is_done() {
+            *self.outstanding.entry(destination).or_default() += 1;
+        }
+        self.transactions.insert(id, Entry { destination, transaction });
+        Ok(())
+    }
+
+    pub fn poll_transmit(&mut self) -> Option<(SocketAddr, &[u8])> {
+        self.transactions.values_mut().find_map(|Entry { destination, transaction }| {
+            transaction.poll_transmit().map(|request| (*destination, request))
+        })
+    }
+
+    #[inline]
+    pub fn poll_timeout(&self) -> Option<I> {
+        self.transactions.values().filter_map(|entry| entry.transaction.poll_timeout()).min()
+    }
+
+    pub fn handle_timeout(&mut self, now: I) {
+        for Entry { destination, transaction } in self.transactions.values_mut() {
+            if transaction.poll_timeout().is_some_and(|deadline| deadline <= now) {
+                transaction.handle_timeout(now);
+                if transaction.is_done() {
+                    complete(&mut self.outstanding, destination);
+                }
+            }
+        }
+    }
+
+    // Responses matching no transaction are dropped and counted as possibly spoofed
+    pub fn handle_input(
+        &mut self,
+        src: &[u8],
+    ) -> Result<Option<(TransactionId, Outcome)>, StunError> {
+        let header = super::transaction::decode_header(src)?;
+        if !header.class().is_response() {
+            return Ok(None);
+        }
+        let id = header.transaction_id();
+        let Some(Entry { destination, transaction }) = self.transactions.get_mut(&id) else {
+            self.unmatched += 1;
+            return Ok(None);
+        };
+        let Some(outcome) = transaction.handle_input(src)? else {
+            return Ok(None);
+        };
+        complete(&mut self.outstanding, destination);
+        Ok(Some((id, outcome)))
+    }
+
+    // Removes a finished transaction, either answered or timed out
+    pub fn poll_completed(&mut self) -> Option<(SocketAddr, ClientTransaction<M, I>)> {
+        let id = *self.transactions.iter().find(|(_, entry)| entry.transaction.is_done())?.0;
+        self.transactions.remove(&id).map(|entry| (entry.destination, entry.transaction))
+    }
+
+    #[inline]
+    pub fn get(&self, id: &TransactionId) -> Option<&ClientTransaction<M, I>> {
+        self.transactions.get(id).map(|entry| &entry.transaction)
+    }
+
+    // Cancels a transaction, whether or not it finished
+    pub fn remove(&mut self, id: &TransactionId) -> Option<(SocketAddr, ClientTransaction<M, I>)> {
+        let Entry { destination, transaction } = self.transactions.remove(id)?;
+        if !transaction.is_done() {
+            complete(&mut self.outstanding, &destination);
+        }
+        Some((destination, transaction))
+    }
+
+    #[inline]
+    pub fn outstanding(&self, destination: &SocketAddr) -> usize {
+        self.outstanding.get(destination).copied().unwrap_or_default()
+    }
+
+    #[inline]
+    pub fn unmatched(&self) -> u64 {
+        self.unmatched
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.transactions.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.transactions.is_empty()
+    }
+}
+
+impl<M: AsRef<[u8]>, I: Instant> Default for TransactionTable<M, I> {
+    #[inline]
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_MAX_PER_DESTINATION)
+    }
+}
+
+#[inline]
+fn complete(outstanding: &mut BTreeMap<SocketAddr, usize>, destination: &SocketAddr) {
+    if let Some(count) = outstanding.get_mut(destination) {
+        *count -= 1;
+        if *count == 0 {
+            outstanding.remove(destination);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        build::MessageBuilder, client::Transport, error::StunErrorKind, test_data::assert_ok,
+        Class, Header, Method,
+    };
+    use core::time::Duration;
+
+    type Table = TransactionTable<[u8; Header::LEN], Duration>;
+
+    fn transaction(i: u8) -> ClientTransaction<[u8; Header::LEN], Duration> {
+        let mut buf = [0; Header::LEN];
+        let id = TransactionId::new([i; 12]);
+        MessageBuilder::new(Class::Request, Method::BINDING, id, &mut buf).unwrap().finish();
+        assert_ok!(
+            ClientTransaction::new(buf, Transport::Unreliable, Duration::ZERO),
+            "bad request"
+        )
+    }
+
+    fn response(i: u8) -> [u8; Header::LEN] {
+        let mut buf = [0; Header::LEN];
+        let id = TransactionId::new([i; 12]);
+        Header::new(Class::SuccessResponse, Method::BINDING, 0, id).encode(&mut buf);
+        buf
+    }
+
+    #[test]
+    fn test_table() {
+        let (a, b) = ("192.0.2.1:3478".parse().unwrap(), "192.0.2.2:3478".parse().unwrap());
+        let mut table = Table::new(2);
+        assert_ok!(table.insert(a, transaction(1)), "bad insert");
+        assert_ok!(table.insert(a, transaction(2)), "bad insert");
+        let err = table.insert(a, transaction(3)).unwrap_err();
+        assert_eq!(err.error_kind(), StunErrorKind::InvalidParameter);
+        assert!(table.insert(b, transaction(1)).is_err());
+        assert_ok!(table.insert(b, transaction(3)), "bad insert");
+        let mut sent = 0;
+        while table.poll_transmit().is_some() {
+            sent += 1;
+        }
+        assert_eq!(sent, 3);
+        assert_eq!(table.poll_timeout(), Some(Duration::from_millis(500)));
+
+        assert_eq!(assert_ok!(table.handle_input(&response(9)), "bad input"), None);
+        assert_eq!(table.unmatched(), 1);
+        let matched = assert_ok!(table.handle_input(&response(2)), "bad input");
+        assert_eq!(matched, Some((TransactionId::new([2; 12]), Outcome::Success)));
+        assert_eq!(assert_ok!(table.handle_input(&response(2)), "bad input"), None);
+        assert_eq!(table.unmatched(), 1);
+        assert_eq!(table.outstanding(&a), 1);
+        assert_ok!(table.insert(a, transaction(4)), "bad insert");
+
+        let (destination, done) = table.poll_completed().expect("completed");
+        assert_eq!((destination, done.outcome()), (a, Some(Outcome::Success)));
+        assert!(table.poll_completed().is_none());
+        while let Some(deadline) = table.poll_timeout() {
+            table.handle_timeout(deadline);
+            while table.poll_transmit().is_some() {}
+        }
+        assert_eq!(table.len(), 3);
+        assert_eq!((table.outstanding(&a), table.outstanding(&b)), (0, 0));
+        while let Some((_, done)) = table.poll_completed() {
+            assert_eq!(done.outcome(), Some(Outcome::Timeout));
+        }
+        assert!(table.is_empty());
+    }
+}
@@ -0,0 +1,272 @@
+use crate::{
+    error::{new_error, StunError},
+    header::{Header, TransactionId},
+    time::Instant,
+    util,
+};
+use core::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Transport {
+    // Retransmits with exponential backoff
+    Unreliable,
+    // Sends once and waits for Ti
+    Reliable,
+}
+
+// https://datatracker.ietf.org/doc/html/rfc8489#section-6.2.1
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TransactionConfig {
+    pub rto: Duration,
+    pub rc: u32,
+    pub rm: u32,
+    pub ti: Duration,
+}
+
+impl TransactionConfig {
+    pub const DEFAULT: Self =
+        Self { rto: Duration::from_millis(500), rc: 7, rm: 16, ti: Duration::from_millis(39_500) };
+}
+
+impl Default for TransactionConfig {
+    #[inline]
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Outcome {
+    Success,
+    ErrorResponse,
+    Timeout,
+}
+
+// Sans-io, the caller sends whatever `poll_transmit` returns, wakes up at `poll_timeout` to call
+// `handle_timeout`, and feeds every received message to `handle_input`.
+#[derive(Debug, Clone)]
+pub struct ClientTransaction<M, I> {
+    request: M,
+    transaction_id: TransactionId,
+    transport: Transport,
+    config: TransactionConfig,
+    interval: Duration,
+    sent: u32,
+    transmit: bool,
+    deadline: Option<I>,
+    outcome: Option<Outcome>,
+}
+
+impl<M: AsRef<[u8]>, I: Instant> ClientTransaction<M, I> {
+    #[inline]
+    pub fn new(request: M, transport: Transport, now: I) -> Result<Self, StunError> {
+        Self::with_config(request, transport, TransactionConfig::DEFAULT, now)
+    }
+
+    pub fn with_config(
+        request: M,
+        transport: Transport,
+        config: TransactionConfig,
+        now: I,
+    ) -> Result<Self, StunError> {
+        let header = decode_header(request.as_ref())?;
+        if !header.class().is_request() {
+            new_error!(NotARequest, InvalidParameter, "client transactions can only send requests");
+            return Err(NotARequest.into());
+        }
+        let mut transaction = Self {
+            request,
+            transaction_id: header.transaction_id(),
+            transport,
+            config,
+            interval: config.rto,
+            sent: 0,
+            transmit: true,
+            deadline: None,
+            outcome: None,
+        };
+        transaction.deadline = Some(now + transaction.next_interval());
+        Ok(transaction)
+    }
+
+    // The request, whenever a transmission or retransmission is due
+    #[inline]
+    pub fn poll_transmit(&mut self) -> Option<&[u8]> {
+        if !core::mem::take(&mut self.transmit) {
+            return None;
+        }
+        self.sent += 1;
+        Some(self.request.as_ref())
+    }
+
+    #[inline]
+    pub fn poll_timeout(&self) -> Option<I> {
+        self.deadline
+    }
+
+    pub fn handle_timeout(&mut self, now: I) {
+        let Some(deadline) = self.deadline.filter(|&deadline| deadline <= now) else {
+            return;
+        };
+        if self.transport == Transport::Reliable || self.sent >= self.config.rc {
+            self.finish(Outcome::Timeout);
+            return;
+        }
+        self.interval = self.interval.saturating_mul(2);
+        self.transmit = true;
+        self.deadline = Some(deadline + self.next_interval());
+    }
+
+    // Returns the outcome if the message answers this transaction, other messages are ignored
+    pub fn handle_input(&mut self, src: &[u8]) -> Result<Option<Outcome>, StunError> {
+        let header = decode_header(src)?;
+        if self.outcome.is_some()
+            || header.is_rfc3489()
+            || header.transaction_id() != self.transaction_id
+            || !header.class().is_response()
+        {
+            return Ok(None);
+        }
+        let outcome =
+            if header.class().is_success() { Outcome::Success } else { Outcome::ErrorResponse };
+        self.finish(outcome);
+        Ok(Some(outcome))
+    }
+
+    #[inline]
+    pub fn transaction_id(&self) -> &TransactionId {
+        &self.transaction_id
+    }
+
+    #[inline]
+    pub fn request(&self) -> &[u8] {
+        self.request.as_ref()
+    }
+
+    #[inline]
+    pub fn outcome(&self) -> Option<Outcome> {
+        self.outcome
+    }
+
+    #[inline]
+    pub fn is_done(&self) -> bool {
+        self.outcome.is_some()
+    }
+
+    #[inline]
+    pub fn into_request(self) -> M {
+        self.request
+    }
+
+    // The wait after the upcoming transmission, Rm * RTO after the last one
+    #[inline]
+    fn next_interval(&self) -> Duration {
+        match self.transport {
+            Transport::Reliable => self.config.ti,
+            Transport::Unreliable if self.sent + 1 >= self.config.rc => {
+                self.config.rto.saturating_mul(self.config.rm)
+            }
+            Transport::Unreliable => self.interval,
+        }
+    }
+
+    #[inline]
+    fn finish(&mut self, outcome: Outcome) {
+        (self.outcome, self.deadline, self.transmit) = (Some(outcome), None, false);
+    }
+}
+
+#[inline]
+pub(crate) fn decode_header(src: &[u8]) -> Result<Header, StunError> {
+    if src.len() < Header::LEN {
+        new_error!(
+            HeaderTooBig { len: usize },
+            BufferTooSmall,
+            "src of len {len} can't fit a header of len 20",
+        );
+        return Err(HeaderTooBig::new(src.len()).into());
+    }
+    Header::decode(util::split_array_ref(src).0)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{build::MessageBuilder, test_data::assert_ok, Class, Method};
+
+    fn request(id: TransactionId) -> [u8; Header::LEN] {
+        let mut buf = [0; Header::LEN];
+        MessageBuilder::new(Class::Request, Method::BINDING, id, &mut buf).unwrap().finish();
+        buf
+    }
+
+    fn response(class: Class, id: TransactionId) -> [u8; Header::LEN] {
+        let mut buf = [0; Header::LEN];
+        Header::new(class, Method::BINDING, 0, id).encode(&mut buf);
+        buf
+    }
+
+    // Runs to completion, returning the times of every transmission and the final outcome
+    fn run<M: AsRef<[u8]>>(mut transaction: ClientTransaction<M, Duration>) -> ([u64; 8], u64) {
+        let (mut sent, mut i) = ([0; 8], 0);
+        let mut now = Duration::ZERO;
+        loop {
+            if transaction.poll_transmit().is_some() {
+                sent[i] = now.as_millis() as u64;
+                i += 1;
+            }
+            match transaction.poll_timeout() {
+                Some(deadline) => now = deadline,
+                None => return (sent, now.as_millis() as u64),
+            }
+            transaction.handle_timeout(now);
+        }
+    }
+
+    #[test]
+    fn test_retransmissions() {
+        let id = TransactionId::new([1; 12]);
+        let transaction = assert_ok!(
+            ClientTransaction::new(request(id), Transport::Unreliable, Duration::ZERO),
+            "bad request"
+        );
+        let (sent, timeout) = run(transaction);
+        assert_eq!(sent, [0, 500, 1500, 3500, 7500, 15500, 31500, 0]);
+        assert_eq!(timeout, 39500);
+        let transaction = assert_ok!(
+            ClientTransaction::new(request(id), Transport::Reliable, Duration::ZERO),
+            "bad request"
+        );
+        assert_eq!(run(transaction), ([0; 8], 39500));
+    }
+
+    #[test]
+    fn test_response() {
+        let (id, other) = (TransactionId::new([1; 12]), TransactionId::new([2; 12]));
+        let mut transaction = assert_ok!(
+            ClientTransaction::new(request(id), Transport::Unreliable, Duration::ZERO),
+            "bad request"
+        );
+        assert_eq!(transaction.poll_transmit(), Some(&request(id)[..]));
+        assert_eq!(transaction.poll_transmit(), None);
+        transaction.handle_timeout(Duration::from_millis(499));
+        assert_eq!(transaction.poll_transmit(), None);
+        let res = transaction.handle_input(&response(Class::SuccessResponse, other));
+        assert_eq!(assert_ok!(res, "bad response"), None);
+        let res = transaction.handle_input(&response(Class::Request, id));
+        assert_eq!(assert_ok!(res, "bad response"), None);
+        let res = transaction.handle_input(&response(Class::ErrorResponse, id));
+        assert_eq!(assert_ok!(res, "bad response"), Some(Outcome::ErrorResponse));
+        assert_eq!(transaction.outcome(), Some(Outcome::ErrorResponse));
+        assert_eq!(transaction.poll_timeout(), None);
+        let res = transaction.handle_input(&response(Class::SuccessResponse, id));
+        assert_eq!(assert_ok!(res, "bad response"), None);
+        assert!(transaction.handle_input(&[0; 4]).is_err());
+        let err = ClientTransaction::new(
+            response(Class::SuccessResponse, id),
+            Transport::Reliable,
+            Duration::ZERO,
+        );
+        assert!(err.is_err());
+    }
+}
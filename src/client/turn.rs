@@ -0,0 +1,746 @@
+use crate::{
+    attribute::{
+        rfc8489::{parsed, XorMappedAddress},
+        rfc8656::{
+            self, ChannelNumber, Data, Lifetime, RequestedTransport, XorPeerAddress,
+            XorRelayedAddress,
+        },
+        Type,
+    },
+    auth::ClientAuthenticator,
+    build::{Buffer, MessageBuilder},
+    channel_data::{ChannelData, Framing},
+    client::{ClientTransaction, Outcome, TransactionTable, Transport},
+    error::{new_error, StunError},
+    header::{classify, Class, Method, Packet, TransactionId},
+    net::{IpAddr, SocketAddr},
+    parse::{AttributeOrdering, MessageParser, ParseOptions, RawAttribute},
+    time::Instant,
+};
+use alloc::{collections::BTreeMap, collections::VecDeque, vec::Vec};
+use core::{mem::MaybeUninit, time::Duration};
+
+const MAX_ATTRIBUTES: usize = 32;
+
+// https://datatracker.ietf.org/doc/html/rfc8489#section-6.2.1
+// Requests made by the caller beyond this wait in a queue, refreshes are always sent right away
+const MAX_OUTSTANDING: usize = 10;
+
+// Attributes after the integrity are unauthenticated, so they are dropped
+const OPTIONS: ParseOptions = ParseOptions::new().with_ordering(AttributeOrdering::Trim);
+
+// https://datatracker.ietf.org/doc/html/rfc8656#section-7.2
+const DEFAULT_LIFETIME: u32 = 600;
+
+// How long before expiry allocations, permissions and channels are refreshed
+const REFRESH_MARGIN: Duration = Duration::from_secs(60);
+
+// https://datatracker.ietf.org/doc/html/rfc8656#section-9
+const PERMISSION_LIFETIME: Duration = Duration::from_secs(300);
+
+// https://datatracker.ietf.org/doc/html/rfc8656#section-12
+const CHANNEL_LIFETIME: Duration = Duration::from_secs(600);
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum TurnEvent {
+    Allocated { relayed: SocketAddr, mapped: SocketAddr },
+    Released,
+    PermissionCreated(IpAddr),
+    ChannelBound { channel: u16, peer: SocketAddr },
+    // The request timed out if there's no error code
+    Failed { method: Method, code: Option<u16> },
+}
+
+// https://datatracker.ietf.org/doc/html/rfc8656
+// Sans-io, like `ClientTransaction`: the caller sends whatever `poll_transmit` returns to the
+// server, wakes up at `poll_timeout` to call `handle_timeout`, feeds every message from the server
+// to `handle_input`, and reacts to `poll_event`. The allocation, permissions and channels are
+// refreshed a minute before they expire, and `transaction_ids` should return random ids.
+pub struct TurnClient<G, I> {
+    server: SocketAddr,
+    transport: Transport,
+    transaction_ids: G,
+    auth: ClientAuthenticator,
+    transactions: TransactionTable<Vec<u8>, I>,
+    requests: BTreeMap<TransactionId, (Request, bool)>,
+    queued: VecDeque<Request>,
+    allocation: Option<Allocation<I>>,
+    permissions: BTreeMap<IpAddr, Option<I>>,
+    channels: BTreeMap<SocketAddr, (u16, Option<I>)>,
+    next_channel: u16,
+    // The channel numbers of binds the server refused, to be used before `next_channel`
+    free_channels: Vec<u16>,
+    events: VecDeque<TurnEvent>,
+}
+
+impl<G: FnMut() -> TransactionId, I: Instant> TurnClient<G, I> {
+    pub fn new(
+        server: SocketAddr,
+        transport: Transport,
+        username: &str,
+        password: &str,
+        transaction_ids: G,
+    ) -> Result<Self, StunError> {
+        Ok(Self {
+            server,
+            transport,
+            transaction_ids,
+            auth: ClientAuthenticator::new(username, password)?,
+            transactions: TransactionTable::new(usize::MAX),
+            requests: BTreeMap::new(),
+            queued: VecDeque::new(),
+            allocation: None,
+            permissions: BTreeMap::new(),
+            channels: BTreeMap::new(),
+            next_channel: ChannelData::MIN_CHANNEL,
+            free_channels: Vec::new(),
+            events: VecDeque::new(),
+        })
+    }
+
+    #[inline]
+    pub fn server(&self) -> SocketAddr {
+        self.server
+    }
+
+    #[inline]
+    pub fn relayed(&self) -> Option<SocketAddr> {
+        self.allocation.as_ref().map(|allocation| allocation.relayed)
+    }
+
+    #[inline]
+    pub fn mapped(&self) -> Option<SocketAddr> {
+        self.allocation.as_ref().map(|allocation| allocation.mapped)
+    }
+
+    #[inline]
+    pub fn has_permission(&self, peer: &IpAddr) -> bool {
+        self.permissions.contains_key(peer)
+    }
+
+    #[inline]
+    pub fn channel(&self, peer: &SocketAddr) -> Option<u16> {
+        self.channels.get(peer).map(|&(channel, _)| channel)
+    }
+
+    // https://datatracker.ietf.org/doc/html/rfc8656#section-7.1
+    pub fn allocate(&mut self, now: I) -> Result<(), StunError> {
+        let pending = self
+            .pending()
+            .any(|request| matches!(request, Request::Allocate { .. } | Request::Reclaim));
+        if self.allocation.is_some() || pending {
+            new_error!(AllocationExists, InvalidParameter, "an allocation already exists");
+            return Err(AllocationExists.into());
+        }
+        self.submit(Request::Allocate { reclaimed: false }, now)
+    }
+
+    // https://datatracker.ietf.org/doc/html/rfc8656#section-8.1
+    // Deletes the allocation by refreshing it with a lifetime of 0
+    pub fn release(&mut self, now: I) -> Result<(), StunError> {
+        self.ensure_allocated()?;
+        self.submit(Request::Refresh(0), now)
+    }
+
+    // https://datatracker.ietf.org/doc/html/rfc8656#section-10.1
+    pub fn create_permission(&mut self, peer: IpAddr, now: I) -> Result<(), StunError> {
+        self.ensure_allocated()?;
+        self.submit(Request::Permission(peer), now)
+    }
+
+    // https://datatracker.ietf.org/doc/html/rfc8656#section-11.1
+    // Returns the channel number, an already bound peer keeps its channel.
+    pub fn bind_channel(&mut self, peer: SocketAddr, now: I) -> Result<u16, StunError> {
+        self.ensure_allocated()?;
+        let pending = self.pending().find_map(|request| match *request {
+            Request::ChannelBind(channel, bound) if bound == peer => Some(channel),
+            _ => None,
+        });
+        if let Some(channel) = self.channel(&peer).or(pending) {
+            return Ok(channel);
+        }
+        if self.free_channels.is_empty() && self.next_channel > ChannelData::MAX_CHANNEL {
+            new_error!(NoChannelLeft, InvalidParameter, "all channel numbers are already bound");
+            return Err(NoChannelLeft.into());
+        }
+        let channel = self.free_channels.pop().unwrap_or_else(|| {
+            self.next_channel += 1;
+            self.next_channel - 1
+        });
+        self.submit(Request::ChannelBind(channel, peer), now)?;
+        Ok(channel)
+    }
+
+    // Wraps data for a peer in ChannelData if a channel is bound, or a SEND indication otherwise
+    pub fn send<'b, B: Buffer + ?Sized>(
+        &mut self,
+        peer: SocketAddr,
+        data: &[u8],
+        buf: &'b mut B,
+    ) -> Result<&'b mut [u8], StunError> {
+        if let Some(channel) = self.channel(&peer) {
+            let (message, framing) = (ChannelData::new(channel, data)?, self.framing());
+            let len = message.encoded_len(framing);
+            message.encode(buf.reserve(0, len), framing)?;
+            return Ok(buf.finish(len));
+        }
+        let transaction_id = (self.transaction_ids)();
+        let mut builder =
+            MessageBuilder::new(Class::Indication, Method::SEND, transaction_id, buf)?;
+        builder.add(&XorPeerAddress::new(peer))?.add(&Data::new(data))?;
+        Ok(builder.finish())
+    }
+
+    #[inline]
+    pub fn poll_transmit(&mut self) -> Option<&[u8]> {
+        self.transactions.poll_transmit().map(|(_, request)| request)
+    }
+
+    #[inline]
+    pub fn poll_event(&mut self) -> Option<TurnEvent> {
+        self.events.pop_front()
+    }
+
+    pub fn poll_timeout(&self) -> Option<I> {
+        let allocation = self.allocation.as_ref().and_then(|allocation| allocation.refresh);
+        let permissions = self.permissions.values().flatten();
+        let channels = self.channels.values().filter_map(|(_, refresh)| refresh.as_ref());
+        let refreshes = allocation.into_iter().chain(permissions.chain(channels).copied());
+        refreshes.chain(self.transactions.poll_timeout()).min()
+    }
+
+    pub fn handle_timeout(&mut self, now: I) -> Result<(), StunError> {
+        self.transactions.handle_timeout(now);
+        while let Some((_, transaction)) = self.transactions.poll_completed() {
+            if let Some((request, _)) = self.requests.remove(transaction.transaction_id()) {
+                self.fail(request, None, now)?;
+            }
+        }
+        self.start_queued(now)?;
+        let is_due = |refresh: &Option<I>| refresh.is_some_and(|refresh| refresh <= now);
+        let mut due = Vec::new();
+        if self.allocation.as_ref().is_some_and(|allocation| is_due(&allocation.refresh)) {
+            due.push(Request::Refresh(DEFAULT_LIFETIME));
+        }
+        let permissions = self.permissions.iter().filter(|(_, refresh)| is_due(refresh));
+        due.extend(permissions.map(|(&peer, _)| Request::Permission(peer)));
+        let channels = self.channels.iter().filter(|(_, (_, refresh))| is_due(refresh));
+        due.extend(channels.map(|(&peer, &(channel, _))| Request::ChannelBind(channel, peer)));
+        // A refresh is only cleared once its request started, so a failure retries it later
+        for request in due {
+            self.start(request, false, now)?;
+            if let Some(refresh) = self.refresh_mut(request) {
+                *refresh = None;
+            }
+        }
+        Ok(())
+    }
+
+    // Handles a message from the server, returning the peer and data of DATA indications and
+    // ChannelData messages.
+    pub fn handle_input<'a>(
+        &mut self,
+        src: &'a [u8],
+        now: I,
+    ) -> Result<Option<(SocketAddr, &'a [u8])>, StunError> {
+        match classify(src) {
+            Packet::ChannelData { .. } => {
+                let message = ChannelData::decode(src, self.framing())?;
+                let mut channels = self.channels.iter();
+                let peer = channels.find(|(_, &(channel, _))| channel == message.channel());
+                Ok(peer.map(|(&peer, _)| (peer, message.data())))
+            }
+            Packet::Stun(header) if header.class() == Class::Indication => {
+                let mut attrs = [MaybeUninit::<RawAttribute>::uninit(); MAX_ATTRIBUTES];
+                let msg = MessageParser::from_complete_message_with(src, &mut attrs, OPTIONS)?;
+                if msg.method() != Method::DATA {
+                    return Ok(None);
+                }
+                let peer = msg.find::<XorPeerAddress>(XorPeerAddress::TYPE)?;
+                let data = msg.find::<rfc8656::parsed::Data>(Type::DATA)?;
+                Ok(peer.zip(data).map(|(peer, data)| (*peer.addr(), data.into_inner())))
+            }
+            Packet::Stun(_) => {
+                let handled = self.handle_response(src, now);
+                self.start_queued(now)?;
+                handled.map(|_| None)
+            }
+            _ => Ok(None),
+        }
+    }
+
+    fn handle_response(&mut self, src: &[u8], now: I) -> Result<(), StunError> {
+        let Some((id, outcome)) = self.transactions.handle_input(src)? else {
+            return Ok(());
+        };
+        self.transactions.remove(&id);
+        let Some((request, retried)) = self.requests.remove(&id) else {
+            return Ok(());
+        };
+        let mut attrs = [MaybeUninit::<RawAttribute>::uninit(); MAX_ATTRIBUTES];
+        let msg = match MessageParser::from_complete_message_with(src, &mut attrs, OPTIONS) {
+            Ok(msg) => msg,
+            Err(err) => {
+                self.fail(request, None, now)?;
+                return Err(err);
+            }
+        };
+        if outcome == Outcome::Success {
+            return match self.auth.verify(&msg) {
+                Ok(()) => self.succeed(request, &msg, now),
+                Err(_) => self.fail(request, None, now),
+            };
+        }
+        if self.auth.handle_error(&msg, retried)? {
+            return self.start(request, true, now);
+        }
+        let code = msg.find::<parsed::ErrorCode>(Type::ERROR_CODE)?.map(|code| code.code());
+        self.fail(request, code, now)
+    }
+
+    fn succeed(&mut self, request: Request, msg: &MessageParser, now: I) -> Result<(), StunError> {
+        let lifetime = msg.find::<Lifetime>(Lifetime::TYPE).ok().flatten();
+        match request {
+            Request::Allocate { .. } => {
+                let relayed = msg.find::<XorRelayedAddress>(XorRelayedAddress::TYPE);
+                let mapped = msg.find::<XorMappedAddress>(XorMappedAddress::TYPE);
+                let (Ok(Some(relayed)), Ok(Some(mapped)), Some(lifetime)) =
+                    (relayed, mapped, lifetime)
+                else {
+                    return self.fail(request, None, now);
+                };
+                let (relayed, mapped) = (*relayed.addr(), *mapped.addr());
+                let refresh = Some(now + refresh_in(lifetime_secs(lifetime.value())));
+                self.allocation = Some(Allocation { relayed, mapped, refresh });
+                self.events.push_back(TurnEvent::Allocated { relayed, mapped });
+            }
+            Request::Reclaim => {
+                return self.start(Request::Allocate { reclaimed: true }, false, now)
+            }
+            Request::Refresh(0) => {
+                self.clear();
+                self.events.push_back(TurnEvent::Released);
+            }
+            Request::Refresh(requested) => {
+                let lifetime = lifetime.map_or(requested, |lifetime| lifetime.value());
+                if let Some(allocation) = &mut self.allocation {
+                    allocation.refresh = Some(now + refresh_in(lifetime_secs(lifetime)));
+                }
+            }
+            Request::Permission(peer) => {
+                let refresh = now + refresh_in(PERMISSION_LIFETIME);
+                if self.permissions.insert(peer, Some(refresh)).is_none() {
+                    self.events.push_back(TurnEvent::PermissionCreated(peer));
+                }
+            }
+            Request::ChannelBind(channel, peer) => {
+                self.permissions.insert(peer.ip(), Some(now + refresh_in(PERMISSION_LIFETIME)));
+                let refresh = Some(now + refresh_in(CHANNEL_LIFETIME));
+                if self.channels.insert(peer, (channel, refresh)).is_none() {
+                    self.events.push_back(TurnEvent::ChannelBound { channel, peer });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn fail(&mut self, request: Request, code: Option<u16>, now: I) -> Result<(), StunError> {
+        match request {
+            // https://datatracker.ietf.org/doc/html/rfc8656#section-7.3
+            // The success response to an earlier Allocate was lost, so the allocation is deleted
+            // and requested again
+            Request::Allocate { reclaimed: false } if code == Some(437) => {
+                return self.start(Request::Reclaim, false, now);
+            }
+            // https://datatracker.ietf.org/doc/html/rfc8656#section-8.3
+            Request::Refresh(_) if code == Some(437) => self.clear(),
+            Request::Permission(peer) => {
+                self.permissions.remove(&peer);
+            }
+            // A refused bind leaves the channel number unused, unlike a timed out one
+            Request::ChannelBind(channel, peer) => {
+                let bound = self.channels.remove(&peer).is_some();
+                if !bound && code.is_some() {
+                    self.free_channels.push(channel);
+                }
+            }
+            _ => {}
+        }
+        self.events.push_back(TurnEvent::Failed { method: request.method(), code });
+        Ok(())
+    }
+
+    fn start(&mut self, request: Request, retried: bool, now: I) -> Result<(), StunError> {
+        let transaction_id = (self.transaction_ids)();
+        let mut buf = Vec::new();
+        let mut builder =
+            MessageBuilder::new(Class::Request, request.method(), transaction_id, &mut buf)?;
+        match request {
+            Request::Allocate { .. } => {
+                builder.add(&RequestedTransport::new(RequestedTransport::UDP))?
+            }
+            Request::Reclaim => builder.add(&Lifetime::new(0))?,
+            Request::Refresh(lifetime) => builder.add(&Lifetime::new(lifetime))?,
+            Request::Permission(peer) => {
+                builder.add(&XorPeerAddress::new(SocketAddr::new(peer, 0)))?
+            }
+            Request::ChannelBind(channel, peer) => {
+                builder.add(&ChannelNumber::new(channel)?)?.add(&XorPeerAddress::new(peer))?
+            }
+        };
+        self.auth.add_credentials(&mut builder)?;
+        builder.finish();
+        let transaction = ClientTransaction::new(buf, self.transport, now)?;
+        self.transactions.insert(self.server, transaction)?;
+        self.requests.insert(transaction_id, (request, retried));
+        Ok(())
+    }
+
+    // Requests from the caller wait for earlier ones to complete beyond `MAX_OUTSTANDING`
+    fn submit(&mut self, request: Request, now: I) -> Result<(), StunError> {
+        if self.transactions.outstanding(&self.server) >= MAX_OUTSTANDING {
+            self.queued.push_back(request);
+            return Ok(());
+        }
+        self.start(request, false, now)
+    }
+
+    fn start_queued(&mut self, now: I) -> Result<(), StunError> {
+        while self.transactions.outstanding(&self.server) < MAX_OUTSTANDING {
+            let Some(request) = self.queued.pop_front() else {
+                break;
+            };
+            self.start(request, false, now)?;
+        }
+        Ok(())
+    }
+
+    #[inline]
+    fn pending(&self) -> impl Iterator<Item = &Request> {
+        self.requests.values().map(|(request, _)| request).chain(&self.queued)
+    }
+
+    #[inline]
+    fn refresh_mut(&mut self, request: Request) -> Option<&mut Option<I>> {
+        match request {
+            Request::Refresh(_) => {
+                self.allocation.as_mut().map(|allocation| &mut allocation.refresh)
+            }
+            Request::Permission(peer) => self.permissions.get_mut(&peer),
+            Request::ChannelBind(_, peer) => {
+                self.channels.get_mut(&peer).map(|(_, refresh)| refresh)
+            }
+            _ => None,
+        }
+    }
+
+    #[inline]
+    fn ensure_allocated(&self) -> Result<(), StunError> {
+        if self.allocation.is_some() {
+            return Ok(());
+        }
+        new_error!(NoAllocation, InvalidParameter, "there's no allocation on the server");
+        Err(NoAllocation.into())
+    }
+
+    #[inline]
+    fn clear(&mut self) {
+        self.allocation = None;
+        self.permissions.clear();
+        self.channels.clear();
+        self.next_channel = ChannelData::MIN_CHANNEL;
+        self.free_channels.clear();
+    }
+
+    #[inline]
+    fn framing(&self) -> Framing {
+        match self.transport {
+            Transport::Unreliable => Framing::Datagram,
+            Transport::Reliable => Framing::Stream,
+        }
+    }
+}
+
+struct Allocation<I> {
+    relayed: SocketAddr,
+    mapped: SocketAddr,
+    refresh: Option<I>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Request {
+    // Whether a stale allocation on the 5-tuple was already deleted
+    Allocate { reclaimed: bool },
+    // Deletes the allocation whose success response was lost
+    Reclaim,
+    Refresh(u32),
+    Permission(IpAddr),
+    ChannelBind(u16, SocketAddr),
+}
+
+impl Request {
+    #[inline]
+    fn method(&self) -> Method {
+        match self {
+            Self::Allocate { .. } => Method::ALLOCATE,
+            Self::Reclaim | Self::Refresh(_) => Method::REFRESH,
+            Self::Permission(_) => Method::CREATE_PERMISSION,
+            Self::ChannelBind(..) => Method::CHANNEL_BIND,
+        }
+    }
+}
+
+#[inline]
+fn lifetime_secs(lifetime: u32) -> Duration {
+    Duration::from_secs(lifetime.into())
+}
+
+#[inline]
+fn refresh_in(lifetime: Duration) -> Duration {
+    match lifetime.checked_sub(REFRESH_MARGIN) {
+        Some(refresh) if refresh >= REFRESH_MARGIN => refresh,
+        _ => lifetime / 2,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        attribute::addr::AddressFamily,
+        auth::MemoryStore,
+        header::Header,
+        net::Ipv4Addr,
+        server::turn::{FiveTuple, Relay, RelayError, TurnServer},
+        test_data::assert_ok,
+    };
+    use alloc::vec;
+
+    const USERNAME: &str = "user";
+    const PASSWORD: &str = "password";
+    const REALM: &str = "example.org";
+
+    type Server = TurnServer<TestRelay, MemoryStore, Duration>;
+
+    #[derive(Default)]
+    struct TestRelay {
+        sent: Vec<(SocketAddr, Vec<u8>)>,
+    }
+
+    impl Relay for TestRelay {
+        fn allocate(&mut self, _: AddressFamily, _: bool) -> Result<SocketAddr, RelayError> {
+            Ok(SocketAddr::new(Ipv4Addr::new(203, 0, 113, 1).into(), 49152))
+        }
+
+        fn release(&mut self, _: SocketAddr) {}
+
+        fn send(&mut self, _: SocketAddr, peer: SocketAddr, data: &[u8]) {
+            self.sent.push((peer, data.to_vec()));
+        }
+    }
+
+    fn tuple() -> FiveTuple {
+        let client = SocketAddr::new(Ipv4Addr::new(192, 0, 2, 1).into(), 5000);
+        let server = SocketAddr::new(Ipv4Addr::new(192, 0, 2, 100).into(), 3478);
+        FiveTuple { client, server, protocol: RequestedTransport::UDP }
+    }
+
+    fn client(password: &str) -> TurnClient<impl FnMut() -> TransactionId, Duration> {
+        let mut ids = 0u8;
+        let ids = move || {
+            ids += 1;
+            TransactionId::new([ids; 12])
+        };
+        let client =
+            TurnClient::new(tuple().server, Transport::Unreliable, USERNAME, password, ids);
+        assert_ok!(client, "bad username")
+    }
+
+    fn server() -> Server {
+        let mut store = MemoryStore::new();
+        store.insert(USERNAME, REALM, PASSWORD).unwrap();
+        let server = TurnServer::new(REALM, [1; 32], TestRelay::default(), store).unwrap();
+        server.with_nonce_lifetime(Duration::from_secs(500))
+    }
+
+    // Delivers the client's requests to the server and the responses back
+    fn exchange(
+        client: &mut TurnClient<impl FnMut() -> TransactionId, Duration>,
+        server: &mut Server,
+        now: Duration,
+    ) {
+        let mut out = vec![];
+        while let Some(request) = client.poll_transmit().map(<[u8]>::to_vec) {
+            let res = assert_ok!(server.handle_client(tuple(), &request, now, &mut out), "bad req");
+            let res = res.expect("response");
+            assert!(assert_ok!(client.handle_input(res, now), "bad response").is_none());
+        }
+    }
+
+    #[test]
+    fn test_turn() {
+        let (mut client, mut server, mut out) = (client(PASSWORD), server(), vec![]);
+        let peer = SocketAddr::new(Ipv4Addr::new(198, 51, 100, 1).into(), 6000);
+        let now = Duration::ZERO;
+        assert!(client.create_permission(peer.ip(), now).is_err());
+        assert_ok!(client.allocate(now), "error allocating");
+        assert!(client.allocate(now).is_err());
+        exchange(&mut client, &mut server, now);
+        let relayed = server.allocation(&tuple()).expect("allocation").relayed();
+        let allocated = TurnEvent::Allocated { relayed, mapped: tuple().client };
+        assert_eq!(client.poll_event(), Some(allocated));
+        assert_eq!((client.relayed(), client.mapped()), (Some(relayed), Some(tuple().client)));
+
+        assert_ok!(client.create_permission(peer.ip(), now), "error creating permission");
+        exchange(&mut client, &mut server, now);
+        assert_eq!(client.poll_event(), Some(TurnEvent::PermissionCreated(peer.ip())));
+        let send = assert_ok!(client.send(peer, b"hello", &mut out), "error sending").to_vec();
+        assert!(server.handle_client(tuple(), &send, now, &mut out).unwrap().is_none());
+        let (_, data) = server.handle_peer(relayed, peer, b"hi", &mut out).unwrap().unwrap();
+        let received = assert_ok!(client.handle_input(data, now), "bad data indication");
+        assert_eq!(received, Some((peer, &b"hi"[..])));
+
+        let channel = assert_ok!(client.bind_channel(peer, now), "error binding channel");
+        assert_eq!(client.bind_channel(peer, now).unwrap(), channel);
+        exchange(&mut client, &mut server, now);
+        assert_eq!(client.poll_event(), Some(TurnEvent::ChannelBound { channel, peer }));
+        let send = assert_ok!(client.send(peer, b"hello", &mut out), "error sending").to_vec();
+        assert_eq!(&send[..2], &channel.to_be_bytes());
+        assert!(server.handle_client(tuple(), &send, now, &mut out).unwrap().is_none());
+        assert_eq!(server.relay().sent, vec![(peer, b"hello".to_vec()); 2]);
+        let (_, data) = server.handle_peer(relayed, peer, b"hi", &mut out).unwrap().unwrap();
+        let received = assert_ok!(client.handle_input(data, now), "bad channel data");
+        assert_eq!(received, Some((peer, &b"hi"[..])));
+
+        // the permission twice, then the allocation and channel with a stale nonce
+        for refresh in [240, 480, 540] {
+            let now = Duration::from_secs(refresh);
+            assert_eq!(client.poll_timeout(), Some(now));
+            assert_ok!(client.handle_timeout(now), "error refreshing");
+            exchange(&mut client, &mut server, now);
+            server.handle_timeout(now);
+            assert_eq!(client.poll_event(), None);
+        }
+        let allocation = server.allocation(&tuple()).expect("allocation");
+        assert_eq!(allocation.expires(), Duration::from_secs(1140));
+        assert!(allocation.has_permission(&peer.ip()));
+        assert_eq!(allocation.channel(&peer), Some(channel));
+
+        assert_ok!(client.release(now), "error releasing");
+        exchange(&mut client, &mut server, now);
+        assert_eq!(client.poll_event(), Some(TurnEvent::Released));
+        assert_eq!((client.relayed(), server.allocations()), (None, 0));
+    }
+
+    #[test]
+    fn test_failure() {
+        let (mut client, mut server) = (client("wrong"), server());
+        assert_ok!(client.allocate(Duration::ZERO), "error allocating");
+        exchange(&mut client, &mut server, Duration::ZERO);
+        let failed = TurnEvent::Failed { method: Method::ALLOCATE, code: Some(401) };
+        assert_eq!(client.poll_event(), Some(failed));
+
+        let mut client = self::client(PASSWORD);
+        assert_ok!(client.allocate(Duration::ZERO), "error allocating");
+        while let Some(deadline) = client.poll_timeout() {
+            assert_ok!(client.handle_timeout(deadline), "error timing out");
+            while client.poll_transmit().is_some() {}
+        }
+        let failed = TurnEvent::Failed { method: Method::ALLOCATE, code: None };
+        assert_eq!(client.poll_event(), Some(failed.clone()));
+        assert_eq!(client.relayed(), None);
+
+        // a malformed response still completes the request
+        assert_ok!(client.allocate(Duration::ZERO), "error allocating");
+        let request = client.poll_transmit().expect("request").to_vec();
+        let mut res = [0; 28];
+        let id = TransactionId::new(request[8..20].try_into().unwrap());
+        let header = Header::new(Class::SuccessResponse, Method::ALLOCATE, 8, id);
+        header.encode((&mut res[..20]).try_into().unwrap());
+        res[20..24].copy_from_slice(&[0x00, 0x0D, 0x00, 0x08]);
+        assert!(client.handle_input(&res, Duration::ZERO).is_err());
+        assert_eq!(client.poll_event(), Some(failed));
+        assert!(client.poll_transmit().is_none());
+    }
+
+    #[test]
+    fn test_recovery() {
+        let (mut client, mut server, mut out) = (client(PASSWORD), server(), vec![]);
+        let now = Duration::ZERO;
+        // the success response to the authenticated Allocate is lost
+        assert_ok!(client.allocate(now), "error allocating");
+        let request = client.poll_transmit().expect("request").to_vec();
+        let res = server.handle_client(tuple(), &request, now, &mut out).unwrap().unwrap();
+        client.handle_input(res, now).unwrap();
+        let request = client.poll_transmit().expect("request").to_vec();
+        assert!(server.handle_client(tuple(), &request, now, &mut out).unwrap().is_some());
+        while let Some(deadline) = client.poll_timeout() {
+            assert_ok!(client.handle_timeout(deadline), "error timing out");
+            while client.poll_transmit().is_some() {}
+        }
+        let failed = TurnEvent::Failed { method: Method::ALLOCATE, code: None };
+        assert_eq!(client.poll_event(), Some(failed));
+        // the stale allocation is deleted before allocating again
+        assert_ok!(client.allocate(now), "error allocating");
+        exchange(&mut client, &mut server, now);
+        let relayed = server.allocation(&tuple()).expect("allocation").relayed();
+        let allocated = TurnEvent::Allocated { relayed, mapped: tuple().client };
+        assert_eq!(client.poll_event(), Some(allocated));
+
+        // a refused channel number is reused, and numbering restarts with a new allocation
+        let v6 = SocketAddr::new(Ipv4Addr::LOCALHOST.to_ipv6_mapped().into(), 6000);
+        let channel = assert_ok!(client.bind_channel(v6, now), "error binding channel");
+        exchange(&mut client, &mut server, now);
+        let failed = TurnEvent::Failed { method: Method::CHANNEL_BIND, code: Some(443) };
+        assert_eq!(client.poll_event(), Some(failed));
+        let peer = SocketAddr::new(Ipv4Addr::new(198, 51, 100, 1).into(), 6000);
+        assert_eq!(client.bind_channel(peer, now).unwrap(), channel);
+        exchange(&mut client, &mut server, now);
+        assert_eq!(client.poll_event(), Some(TurnEvent::ChannelBound { channel, peer }));
+        let other = SocketAddr::new(Ipv4Addr::new(198, 51, 100, 2).into(), 6000);
+        assert_eq!(client.bind_channel(other, now).unwrap(), channel + 1);
+        exchange(&mut client, &mut server, now);
+        assert_ok!(client.release(now), "error releasing");
+        exchange(&mut client, &mut server, now);
+        while client.poll_event().is_some() {}
+        assert_ok!(client.allocate(now), "error allocating");
+        exchange(&mut client, &mut server, now);
+        assert_eq!(client.bind_channel(peer, now).unwrap(), ChannelData::MIN_CHANNEL);
+    }
+
+    #[test]
+    fn test_burst() {
+        let (mut client, mut server, mut out) = (client(PASSWORD), server(), vec![]);
+        let now = Duration::ZERO;
+        assert_ok!(client.allocate(now), "error allocating");
+        exchange(&mut client, &mut server, now);
+        assert!(client.poll_event().is_some());
+
+        // requests beyond the outstanding limit wait for earlier ones to complete
+        let peers = (1..=12).map(|i| IpAddr::from(Ipv4Addr::new(198, 51, 100, i)));
+        for peer in peers.clone() {
+            assert_ok!(client.create_permission(peer, now), "error creating permission");
+        }
+        exchange(&mut client, &mut server, now);
+        for peer in peers.clone() {
+            assert_eq!(client.poll_event(), Some(TurnEvent::PermissionCreated(peer)));
+        }
+
+        // refreshes coming due together are all sent at once
+        let now = Duration::from_secs(240);
+        assert_ok!(client.handle_timeout(now), "error refreshing");
+        let mut requests = vec![];
+        while let Some(request) = client.poll_transmit() {
+            requests.push(request.to_vec());
+        }
+        assert_eq!(requests.len(), 12);
+        for request in requests {
+            let res = server.handle_client(tuple(), &request, now, &mut out).unwrap().unwrap();
+            assert!(assert_ok!(client.handle_input(res, now), "bad response").is_none());
+        }
+        server.handle_timeout(Duration::from_secs(300));
+        let allocation = server.allocation(&tuple()).expect("allocation");
+        assert!(peers.clone().all(|peer| allocation.has_permission(&peer)));
+        assert_eq!(client.poll_event(), None);
+    }
+}
@@ -0,0 +1,5 @@
+mod rfc4571;
+mod stream;
+
+pub use rfc4571::{decode_prefixed, encode_prefixed, LengthPrefixed, PrefixedFrame};
+pub use stream::{decode_frame, Frame};
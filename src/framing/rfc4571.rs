@@ -0,0 +1,168 @@
+use crate::{
+    build::Buffer,
+    error::{new_error, StunError},
+};
+
+const PREFIX_LEN: usize = 2;
+
+// https://datatracker.ietf.org/doc/html/rfc4571#section-2
+// Used by ICE-TCP candidates (RFC 6544) for STUN and media alike, use `header::classify` on the
+// packet to tell them apart.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PrefixedFrame<'a> {
+    // The minimum number of additional bytes required to make progress
+    NeedMore(usize),
+    Packet(&'a [u8]),
+}
+
+impl PrefixedFrame<'_> {
+    // The number of bytes to drop from the front of the stream buffer
+    #[inline]
+    pub fn consumed(&self) -> usize {
+        match self {
+            Self::NeedMore(_) => 0,
+            Self::Packet(packet) => PREFIX_LEN + packet.len(),
+        }
+    }
+}
+
+pub fn decode_prefixed(src: &[u8]) -> PrefixedFrame<'_> {
+    let Some((&prefix, rest)) = src.split_first_chunk::<PREFIX_LEN>() else {
+        return PrefixedFrame::NeedMore(PREFIX_LEN - src.len());
+    };
+    let len = u16::from_be_bytes(prefix) as usize;
+    match rest.get(..len) {
+        Some(packet) => PrefixedFrame::Packet(packet),
+        None => PrefixedFrame::NeedMore(len - rest.len()),
+    }
+}
+
+pub fn encode_prefixed<'a>(packet: &[u8], dst: &'a mut [u8]) -> Result<&'a mut [u8], StunError> {
+    let len = prefix(packet.len())?;
+    let frame_len = PREFIX_LEN + packet.len();
+    if dst.len() < frame_len {
+        new_error!(
+            BufferTooSmall { required: u32, actual: u32 },
+            BufferTooSmall,
+            "framed packet requires a buffer of {required} bytes, buffer of length {actual} provided",
+        );
+        return Err(BufferTooSmall::new(frame_len as u32, dst.len() as u32).into());
+    }
+    let (frame, rest) = dst.split_at_mut(frame_len);
+    let (prefix, value) = frame.split_at_mut(PREFIX_LEN);
+    prefix.copy_from_slice(&len);
+    value.copy_from_slice(packet);
+    Ok(rest)
+}
+
+// A `Buffer` that leaves room for the length prefix in front of the message being built
+pub struct LengthPrefixed<'b, B: Buffer + ?Sized> {
+    buf: &'b mut B,
+    len: usize,
+}
+
+impl<'b, B: Buffer + ?Sized> LengthPrefixed<'b, B> {
+    #[inline]
+    pub fn new(buf: &'b mut B) -> Self {
+        Self { buf, len: 0 }
+    }
+
+    // The finished message along with its prefix
+    pub fn frame(&mut self) -> Result<&mut [u8], StunError> {
+        let len = prefix(self.len)?;
+        let frame = self.buf.finish(PREFIX_LEN + self.len);
+        frame[..PREFIX_LEN].copy_from_slice(&len);
+        Ok(frame)
+    }
+}
+
+impl<B: Buffer + ?Sized> Buffer for LengthPrefixed<'_, B> {
+    #[inline]
+    fn reserve(&mut self, off: usize, additional: usize) -> &mut [u8] {
+        let buf = self.buf.reserve(0, PREFIX_LEN + off + additional);
+        buf.get_mut(PREFIX_LEN + off..).unwrap_or_default()
+    }
+
+    #[inline]
+    fn finish(&mut self, len: usize) -> &mut [u8] {
+        self.len = len;
+        &mut self.buf.finish(PREFIX_LEN + len)[PREFIX_LEN..]
+    }
+}
+
+#[inline]
+fn prefix(len: usize) -> Result<[u8; PREFIX_LEN], StunError> {
+    match u16::try_from(len) {
+        Ok(len) => Ok(len.to_be_bytes()),
+        Err(_) => {
+            new_error!(
+                PacketTooLong { len: usize },
+                ValueTooLong,
+                "packet of {len} bytes doesn't fit in a 2 byte length prefix",
+            );
+            Err(PacketTooLong::new(len).into())
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        attribute::UnknownAttribute, error::StunErrorKind, test_data::*, MessageBuilder,
+        MessageParser,
+    };
+    use core::mem::MaybeUninit;
+
+    fn frame<B: Buffer + ?Sized>(msg: &MessageParser, buf: &mut B) -> usize {
+        let mut framed = LengthPrefixed::new(buf);
+        let mut builder = assert_ok!(
+            MessageBuilder::new(msg.class(), msg.method(), *msg.transaction_id(), &mut framed),
+            "error creating builder",
+        );
+        for item in msg.iter_raw() {
+            let attr = assert_ok!(UnknownAttribute::new(item.attr(), item.value()), "bad attr");
+            assert_ok!(builder.add(&attr), "error adding attribute");
+        }
+        builder.finish();
+        assert_ok!(framed.frame(), "error framing").len()
+    }
+
+    #[test]
+    fn test_framing() {
+        let (mut v, mut arr) = ([MaybeUninit::uninit(); 32], [0; 512]);
+        let message = TEST_VECTOR[4].message;
+        let msg = assert_ok!(
+            MessageParser::from_complete_message(message, &mut v),
+            "error parsing raw msg",
+        );
+        let len = frame(&msg, arr.as_mut_slice());
+        assert_eq!(len, PREFIX_LEN + message.len());
+        #[cfg(feature = "alloc")]
+        {
+            let mut vec = alloc::vec::Vec::new();
+            assert_eq!(frame(&msg, &mut vec), len);
+            assert_eq!(vec, arr[..len]);
+        }
+        let rest = assert_ok!(encode_prefixed(b"\x80rtp", &mut arr[len..]), "error encoding");
+        assert_eq!(rest.len(), arr.len() - len - 6);
+        let mut src = &arr[..len + 6];
+        assert_eq!(decode_prefixed(&src[..1]), PrefixedFrame::NeedMore(1));
+        assert_eq!(decode_prefixed(&src[..100]), PrefixedFrame::NeedMore(len - 100));
+        let frame = decode_prefixed(src);
+        assert_eq!(frame, PrefixedFrame::Packet(message));
+        src = &src[frame.consumed()..];
+        assert_eq!(decode_prefixed(src), PrefixedFrame::Packet(b"\x80rtp"));
+        let err = encode_prefixed(b"rtp", &mut [0; 4]).unwrap_err();
+        assert_eq!(err.error_kind(), StunErrorKind::BufferTooSmall);
+        let err = MessageBuilder::new(
+            msg.class(),
+            msg.method(),
+            *msg.transaction_id(),
+            &mut LengthPrefixed::new(&mut [0; 21]),
+        )
+        .map(|_| ())
+        .unwrap_err();
+        assert_eq!(err.error_kind(), StunErrorKind::BufferTooSmall);
+    }
+}
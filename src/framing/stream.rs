@@ -0,0 +1,104 @@
+use crate::{
+    channel_data::{self, ChannelData, Framing},
+    error::{new_error, StunError},
+    header::Header,
+};
+
+// Only the first 4 bytes are needed to tell STUN and ChannelData apart and find the frame length
+const PREFIX_LEN: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Frame<'a> {
+    // The minimum number of additional bytes required to make progress
+    NeedMore(usize),
+    Stun(&'a [u8]),
+    ChannelData(ChannelData<'a>),
+}
+
+impl Frame<'_> {
+    // The number of bytes to drop from the front of the stream buffer
+    #[inline]
+    pub fn consumed(&self) -> usize {
+        match self {
+            Self::NeedMore(_) => 0,
+            Self::Stun(message) => message.len(),
+            Self::ChannelData(data) => data.encoded_len(Framing::Stream),
+        }
+    }
+}
+
+// https://datatracker.ietf.org/doc/html/rfc8656#section-12.5
+// STUN and ChannelData sent back to back over TCP or TLS, ChannelData padded to a multiple of 4.
+pub fn decode_frame(src: &[u8]) -> Result<Frame<'_>, StunError> {
+    let Some(&[b0, b1, l0, l1]) = src.first_chunk::<PREFIX_LEN>() else {
+        return Ok(Frame::NeedMore(PREFIX_LEN - src.len()));
+    };
+    let len = u16::from_be_bytes([l0, l1]);
+    let frame_len = match b0 >> 6 {
+        0b00 => {
+            if len % 4 != 0 {
+                new_error!(
+                    InvalidLen { len: u16 },
+                    InvalidParameter,
+                    "the length of the attributes ({len}) is invalid. it must be multiple of 4",
+                );
+                return Err(InvalidLen::new(len).into());
+            }
+            Header::LEN + len as usize
+        }
+        0b01 => {
+            channel_data::validate_channel(u16::from_be_bytes([b0, b1]))?;
+            ChannelData::HEADER_LEN + channel_data::padded_len(len as usize)
+        }
+        _ => {
+            new_error!(
+                UnknownFrame { first: u8 },
+                InvalidParameter,
+                "a frame starting with {first:#04X} is neither STUN nor ChannelData",
+            );
+            return Err(UnknownFrame::new(b0).into());
+        }
+    };
+    let Some(frame) = src.get(..frame_len) else {
+        return Ok(Frame::NeedMore(frame_len - src.len()));
+    };
+    Ok(match b0 >> 6 {
+        0b00 => Frame::Stun(frame),
+        _ => Frame::ChannelData(ChannelData::decode(frame, Framing::Stream)?),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{error::StunErrorKind, test_data::*, MessageParser};
+
+    #[test]
+    fn test_decode_frame() {
+        let mut stream = [0; 256];
+        let data = ChannelData::new(0x4001, b"hello").unwrap();
+        let message = TEST_VECTOR[3].message;
+        let rest = data.encode(&mut stream, Framing::Stream).unwrap();
+        rest[..message.len()].copy_from_slice(message);
+        let len = data.encoded_len(Framing::Stream) + message.len();
+        let mut src = &stream[..len];
+        for partial in 0..12 {
+            assert_eq!(assert_ok!(decode_frame(&src[..partial]), "bad frame").consumed(), 0);
+        }
+        assert_eq!(assert_ok!(decode_frame(&src[..2]), "bad frame"), Frame::NeedMore(2));
+        assert_eq!(assert_ok!(decode_frame(&src[..9]), "bad frame"), Frame::NeedMore(3));
+        let frame = assert_ok!(decode_frame(src), "bad frame");
+        assert_eq!(frame, Frame::ChannelData(data));
+        src = &src[frame.consumed()..];
+        assert_eq!(assert_ok!(decode_frame(&src[..30]), "bad frame"), Frame::NeedMore(86));
+        let frame = assert_ok!(decode_frame(src), "bad frame");
+        let Frame::Stun(message) = frame else { panic!("expected a stun frame") };
+        let mut v = [core::mem::MaybeUninit::uninit(); 32];
+        assert_ok!(MessageParser::from_complete_message(message, &mut v), "bad message");
+        assert_eq!(frame.consumed(), src.len());
+        for invalid in [[0x80, 0, 0, 0], [0x50, 0, 0, 0], [0, 1, 0, 2]] {
+            let err = decode_frame(&invalid).unwrap_err();
+            assert_eq!(err.error_kind(), StunErrorKind::InvalidParameter);
+        }
+    }
+}
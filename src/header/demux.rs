@@ -0,0 +1,78 @@
+use crate::{header::Header, util, MAGIC_COOKIE_BYTES};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Packet {
+    Stun(Header),
+    ChannelData { channel: u16, length: u16 },
+    Zrtp,
+    Dtls,
+    Rtp,
+    Quic,
+    Unknown,
+}
+
+// https://datatracker.ietf.org/doc/html/rfc9443#section-7
+// STUN is only reported for a well-formed header carrying the magic cookie, and ChannelData only
+// if the 4 byte header is present, otherwise the packet is Unknown.
+pub fn classify(src: &[u8]) -> Packet {
+    let Some(&first) = src.first() else {
+        return Packet::Unknown;
+    };
+    match first {
+        0..=3 => classify_stun(src),
+        16..=19 => Packet::Zrtp,
+        20..=63 => Packet::Dtls,
+        64..=79 => match *src {
+            [c0, c1, l0, l1, ..] => Packet::ChannelData {
+                channel: u16::from_be_bytes([c0, c1]),
+                length: u16::from_be_bytes([l0, l1]),
+            },
+            _ => Packet::Unknown,
+        },
+        80..=127 | 192..=255 => Packet::Quic,
+        128..=191 => Packet::Rtp,
+        4..=15 => Packet::Unknown,
+    }
+}
+
+#[inline]
+fn classify_stun(src: &[u8]) -> Packet {
+    let Some(header) = src.get(..Header::LEN) else {
+        return Packet::Unknown;
+    };
+    let header: &[u8; Header::LEN] = util::split_array_ref(header).0;
+    if header[4..8] != MAGIC_COOKIE_BYTES {
+        return Packet::Unknown;
+    }
+    Header::decode(header).map_or(Packet::Unknown, Packet::Stun)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_data::*;
+
+    #[test]
+    fn test_classify() {
+        for parts in TEST_VECTOR {
+            let header = assert_ok!(Header::decode(parts.header), "error decoding header");
+            assert_eq!(classify(parts.message), Packet::Stun(header));
+            assert_eq!(classify(&parts.message[..Header::LEN - 1]), Packet::Unknown);
+        }
+        let mut message = SAMPLE_REQUEST;
+        message[4] ^= 1;
+        assert_eq!(classify(&message), Packet::Unknown);
+        assert_eq!(
+            classify(&[0x40, 0x01, 0x00, 0x04, 0, 0, 0, 0]),
+            Packet::ChannelData { channel: 0x4001, length: 4 }
+        );
+        assert_eq!(classify(&[0x4F, 0x01, 0x00]), Packet::Unknown);
+        assert_eq!(classify(&[]), Packet::Unknown);
+        assert_eq!(classify(&[0x08]), Packet::Unknown);
+        assert_eq!(classify(&[0x10]), Packet::Zrtp);
+        assert_eq!(classify(&[0x16, 0xFE, 0xFD]), Packet::Dtls);
+        assert_eq!(classify(&[0x80, 0x00]), Packet::Rtp);
+        assert_eq!(classify(&[0xC0]), Packet::Quic);
+        assert_eq!(classify(&[0x50]), Packet::Quic);
+    }
+}
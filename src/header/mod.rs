@@ -1,13 +1,19 @@
-use crate::error::{new_error, StunError};
+use crate::{
+    error::{new_error, StunError},
+    util,
+};
 
 mod class;
+mod demux;
 mod method;
 mod transaction_id;
 
 pub use class::Class;
+pub use demux::{classify, Packet};
 pub use method::Method;
 pub use transaction_id::TransactionId;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Header {
     pub(crate) class: Class,
     pub(crate) method: Method,
@@ -29,6 +35,19 @@ impl Header {
         Self { class, method, length, transaction_id, magic_cookie: crate::MAGIC_COOKIE }
     }
 
+    // https://datatracker.ietf.org/doc/html/rfc3489#section-11.1
+    // The first 4 bytes of the 16 byte transaction id take the place of the magic cookie.
+    pub const fn new_rfc3489(
+        class: Class,
+        method: Method,
+        length: u16,
+        transaction_id: [u8; 16],
+    ) -> Self {
+        let [c0, c1, c2, c3, t @ ..] = transaction_id;
+        let magic_cookie = u32::from_be_bytes([c0, c1, c2, c3]);
+        Self { class, method, length, magic_cookie, transaction_id: TransactionId(t) }
+    }
+
     pub fn decode(src: &[u8; Self::LEN]) -> Result<Self, StunError> {
         let [m0, m1, l0, l1, c0, c1, c2, c3, t @ ..] = *src;
         let msg_type = u16::from_be_bytes([m0, m1]);
@@ -98,6 +117,23 @@ impl Header {
     pub fn is_rfc3489(&self) -> bool {
         self.magic_cookie() != crate::MAGIC_COOKIE
     }
+
+    #[inline]
+    pub fn rfc3489_transaction_id(&self) -> [u8; 16] {
+        rfc3489_transaction_id(self.magic_cookie, &self.transaction_id)
+    }
+}
+
+#[inline]
+pub(crate) fn rfc3489_transaction_id(
+    magic_cookie: u32,
+    transaction_id: &TransactionId,
+) -> [u8; 16] {
+    let mut id = [0; 16];
+    let (cookie, rest) = util::split_array_exact_mut(&mut id);
+    *cookie = magic_cookie.to_be_bytes();
+    *rest = transaction_id.0;
+    id
 }
 
 #[cfg(test)]
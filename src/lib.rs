@@ -21,12 +21,23 @@ cfg_if::cfg_if! {
 }
 
 pub mod attribute;
+#[cfg(feature = "auth")]
+pub mod auth;
+pub mod build;
+pub mod channel_data;
+pub mod client;
 pub mod error;
+pub mod framing;
 pub mod header;
+pub mod nat;
 pub mod parse;
+pub mod server;
+pub mod time;
 mod util;
 
 pub use attribute::Type as AttributeType;
+pub use build::MessageBuilder;
+pub use channel_data::ChannelData;
 pub use error::StunError;
 pub use header::{Class, Header, Method, TransactionId};
 pub use parse::MessageParser;
@@ -0,0 +1,322 @@
+use crate::{
+    attribute::{
+        rfc5780::{ChangeRequest, OtherAddress},
+        Type,
+    },
+    build::Buffer,
+    error::StunError,
+    header::{Class, TransactionId},
+    nat::Transmit,
+    net::SocketAddr,
+    parse::MessageParser,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MappingBehavior {
+    // The mapped address is the local address
+    NoNat,
+    EndpointIndependent,
+    AddressDependent,
+    AddressAndPortDependent,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FilteringBehavior {
+    EndpointIndependent,
+    AddressDependent,
+    AddressAndPortDependent,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NatBehavior {
+    pub mapping: MappingBehavior,
+    pub filtering: FilteringBehavior,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Failure {
+    // Either the server or its alternate address never answered
+    NoResponse,
+    // The server doesn't support RFC 5780
+    NoOtherAddress,
+    ErrorResponse,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Progress {
+    Pending,
+    Done(NatBehavior),
+    Failed(Failure),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Test {
+    Binding,
+    MappingAlternateIp,
+    MappingAlternate,
+    FilteringChangeBoth,
+    FilteringChangePort,
+}
+
+// https://datatracker.ietf.org/doc/html/rfc5780#section-4.3
+// https://datatracker.ietf.org/doc/html/rfc5780#section-4.4
+// Sans-io, the caller sends each request, retransmits it as needed, and reports either the
+// response or that the transaction timed out. The tests run one at a time, in order.
+#[derive(Debug, Clone)]
+pub struct BehaviorDiscovery {
+    server: SocketAddr,
+    local: SocketAddr,
+    test: Test,
+    transaction_id: Option<TransactionId>,
+    mapped: Option<SocketAddr>,
+    other: Option<SocketAddr>,
+    mapping: Option<MappingBehavior>,
+    progress: Progress,
+}
+
+impl BehaviorDiscovery {
+    #[inline]
+    pub fn new(server: SocketAddr, local: SocketAddr) -> Self {
+        Self {
+            server,
+            local,
+            test: Test::Binding,
+            transaction_id: None,
+            mapped: None,
+            other: None,
+            mapping: None,
+            progress: Progress::Pending,
+        }
+    }
+
+    // Encodes the request of the current test, a new transaction id replaces the previous one
+    pub fn poll_request<'b, B: Buffer + ?Sized>(
+        &mut self,
+        transaction_id: TransactionId,
+        buf: &'b mut B,
+    ) -> Result<Option<Transmit<'b>>, StunError> {
+        if self.progress != Progress::Pending {
+            return Ok(None);
+        }
+        let (server, other) = (self.server, self.other.unwrap_or(self.server));
+        let (destination, change) = match self.test {
+            Test::Binding => (server, None),
+            Test::MappingAlternateIp => (SocketAddr::new(other.ip(), server.port()), None),
+            Test::MappingAlternate => (other, None),
+            Test::FilteringChangeBoth => (server, Some(ChangeRequest::new(true, true))),
+            Test::FilteringChangePort => (server, Some(ChangeRequest::new(false, true))),
+        };
+        let change = change.as_ref().map(|change| change as _);
+        let message = super::binding_request(transaction_id, buf, change)?;
+        self.transaction_id = Some(transaction_id);
+        Ok(Some(Transmit { destination, message }))
+    }
+
+    // Responses to anything but the outstanding request are ignored
+    pub fn handle_response(&mut self, msg: &MessageParser) -> Result<Progress, StunError> {
+        if self.progress != Progress::Pending || self.transaction_id != Some(*msg.transaction_id())
+        {
+            return Ok(self.progress);
+        }
+        match msg.class() {
+            Class::SuccessResponse => {}
+            Class::ErrorResponse => return Ok(self.fail(Failure::ErrorResponse)),
+            _ => return Ok(self.progress),
+        }
+        match self.test {
+            Test::Binding => {
+                let mapped = super::mapped_address(msg)?;
+                let Some(other) = msg.find::<OtherAddress>(Type::OTHER_ADDRESS)? else {
+                    return Ok(self.fail(Failure::NoOtherAddress));
+                };
+                (self.mapped, self.other) = (Some(mapped), Some(*other.addr()));
+                if mapped == self.local {
+                    self.mapping = Some(MappingBehavior::NoNat);
+                    self.test = Test::FilteringChangeBoth;
+                } else {
+                    self.test = Test::MappingAlternateIp;
+                }
+            }
+            Test::MappingAlternateIp => {
+                let mapped = super::mapped_address(msg)?;
+                if Some(mapped) == self.mapped {
+                    self.mapping = Some(MappingBehavior::EndpointIndependent);
+                    self.test = Test::FilteringChangeBoth;
+                } else {
+                    self.mapped = Some(mapped);
+                    self.test = Test::MappingAlternate;
+                }
+            }
+            Test::MappingAlternate => {
+                let mapped = super::mapped_address(msg)?;
+                self.mapping = Some(if Some(mapped) == self.mapped {
+                    MappingBehavior::AddressDependent
+                } else {
+                    MappingBehavior::AddressAndPortDependent
+                });
+                self.test = Test::FilteringChangeBoth;
+            }
+            Test::FilteringChangeBoth => {
+                return Ok(self.done(FilteringBehavior::EndpointIndependent))
+            }
+            Test::FilteringChangePort => return Ok(self.done(FilteringBehavior::AddressDependent)),
+        }
+        self.transaction_id = None;
+        Ok(self.progress)
+    }
+
+    // The outstanding request went unanswered after all retransmissions
+    pub fn handle_timeout(&mut self) -> Progress {
+        if self.progress != Progress::Pending || self.transaction_id.take().is_none() {
+            return self.progress;
+        }
+        match self.test {
+            Test::Binding | Test::MappingAlternateIp | Test::MappingAlternate => {
+                self.fail(Failure::NoResponse)
+            }
+            Test::FilteringChangeBoth => {
+                self.test = Test::FilteringChangePort;
+                self.progress
+            }
+            Test::FilteringChangePort => self.done(FilteringBehavior::AddressAndPortDependent),
+        }
+    }
+
+    #[inline]
+    pub fn progress(&self) -> Progress {
+        self.progress
+    }
+
+    // The server's alternate address, known after the first test
+    #[inline]
+    pub fn other_address(&self) -> Option<SocketAddr> {
+        self.other
+    }
+
+    #[inline]
+    fn fail(&mut self, failure: Failure) -> Progress {
+        self.progress = Progress::Failed(failure);
+        self.progress
+    }
+
+    #[inline]
+    fn done(&mut self, filtering: FilteringBehavior) -> Progress {
+        let mapping = self.mapping.unwrap_or(MappingBehavior::EndpointIndependent);
+        self.progress = Progress::Done(NatBehavior { mapping, filtering });
+        self.progress
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        attribute::rfc8489::XorMappedAddress, build::MessageBuilder, error::StunErrorKind,
+        net::IpAddr, test_data::assert_ok, Method,
+    };
+    use core::mem::MaybeUninit;
+
+    const SERVER: &str = "192.0.2.1:3478";
+    const OTHER: &str = "192.0.2.2:3479";
+    const LOCAL: &str = "10.0.0.1:5000";
+
+    // Answers every request like a NAT would with the given mappings, None dropping the request
+    fn run(
+        mut respond: impl FnMut(SocketAddr, Option<ChangeRequest>) -> Option<SocketAddr>,
+    ) -> Progress {
+        let mut discovery = BehaviorDiscovery::new(SERVER.parse().unwrap(), LOCAL.parse().unwrap());
+        let (mut req, mut res) = ([0; 64], [0; 128]);
+        for i in 0.. {
+            let id = TransactionId::new([i; 12]);
+            let Some(transmit) = assert_ok!(discovery.poll_request(id, &mut req), "bad request")
+            else {
+                return discovery.progress();
+            };
+            let mut v = [MaybeUninit::uninit(); 4];
+            let msg = assert_ok!(
+                MessageParser::from_complete_message(transmit.message, &mut v),
+                "bad msg"
+            );
+            let change = msg.find::<ChangeRequest>(Type::CHANGE_REQUEST).unwrap();
+            let Some(mapped) = respond(transmit.destination, change) else {
+                discovery.handle_timeout();
+                continue;
+            };
+            let mut builder =
+                MessageBuilder::new(Class::SuccessResponse, Method::BINDING, id, &mut res).unwrap();
+            builder.add(&XorMappedAddress::new(mapped)).unwrap();
+            builder.add(&OtherAddress::new(OTHER.parse().unwrap())).unwrap();
+            let msg = assert_ok!(
+                MessageParser::from_complete_message(builder.finish(), &mut v),
+                "bad msg"
+            );
+            assert_ok!(discovery.handle_response(&msg), "bad response");
+        }
+        unreachable!()
+    }
+
+    fn mapped(port: u16) -> SocketAddr {
+        SocketAddr::new("203.0.113.1".parse().unwrap(), port)
+    }
+
+    #[test]
+    fn test_endpoint_independent() {
+        let progress = run(|_, _| Some(mapped(40000)));
+        let mapping = MappingBehavior::EndpointIndependent;
+        let filtering = FilteringBehavior::EndpointIndependent;
+        assert_eq!(progress, Progress::Done(NatBehavior { mapping, filtering }));
+        let progress = run(|_, _| Some(LOCAL.parse().unwrap()));
+        let mapping = MappingBehavior::NoNat;
+        assert_eq!(progress, Progress::Done(NatBehavior { mapping, filtering }));
+    }
+
+    #[test]
+    fn test_dependent() {
+        let progress = run(|destination, change| match change {
+            Some(change) if change.ip() => None,
+            _ => Some(mapped(if destination.ip() == SERVER.parse::<SocketAddr>().unwrap().ip() {
+                40000
+            } else {
+                40001
+            })),
+        });
+        let mapping = MappingBehavior::AddressDependent;
+        let filtering = FilteringBehavior::AddressDependent;
+        assert_eq!(progress, Progress::Done(NatBehavior { mapping, filtering }));
+        let progress = run(|destination, change| match change {
+            Some(_) => None,
+            None => match destination.ip() {
+                IpAddr::V4(ip) => Some(mapped(destination.port() + ip.octets()[3] as u16)),
+                IpAddr::V6(_) => None,
+            },
+        });
+        let mapping = MappingBehavior::AddressAndPortDependent;
+        let filtering = FilteringBehavior::AddressAndPortDependent;
+        assert_eq!(progress, Progress::Done(NatBehavior { mapping, filtering }));
+    }
+
+    #[test]
+    fn test_failure() {
+        assert_eq!(run(|_, _| None), Progress::Failed(Failure::NoResponse));
+        let mut discovery = BehaviorDiscovery::new(SERVER.parse().unwrap(), LOCAL.parse().unwrap());
+        let (mut req, mut res, mut v) = ([0; 64], [0; 64], [MaybeUninit::uninit(); 4]);
+        let id = TransactionId::new([1; 12]);
+        assert_ok!(discovery.poll_request(id, &mut req), "bad request");
+        let mut builder =
+            MessageBuilder::new(Class::SuccessResponse, Method::BINDING, id, &mut res).unwrap();
+        builder.add(&XorMappedAddress::new(mapped(1))).unwrap();
+        let message = builder.finish();
+        let msg = assert_ok!(MessageParser::from_complete_message(message, &mut v), "bad msg");
+        let progress = assert_ok!(discovery.handle_response(&msg), "bad response");
+        assert_eq!(progress, Progress::Failed(Failure::NoOtherAddress));
+        let mut discovery = BehaviorDiscovery::new(SERVER.parse().unwrap(), LOCAL.parse().unwrap());
+        assert_ok!(discovery.poll_request(id, &mut req), "bad request");
+        let message = MessageBuilder::new(Class::SuccessResponse, Method::BINDING, id, &mut res)
+            .unwrap()
+            .finish();
+        let msg = assert_ok!(MessageParser::from_complete_message(message, &mut v), "bad msg");
+        let err = discovery.handle_response(&msg).unwrap_err();
+        assert_eq!(err.error_kind(), StunErrorKind::InvalidParameter);
+        assert_eq!(discovery.progress(), Progress::Pending);
+    }
+}
@@ -0,0 +1,225 @@
+use crate::{
+    attribute::rfc5780::ResponsePort,
+    build::Buffer,
+    error::StunError,
+    header::{Class, TransactionId},
+    nat::{Failure, Transmit},
+    net::SocketAddr,
+    parse::MessageParser,
+};
+use core::time::Duration;
+
+// The primary flow holds the mapping being measured, the secondary flow asks the server to
+// answer through it with RESPONSE-PORT.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Flow {
+    Primary,
+    Secondary,
+}
+
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub enum LifetimeStep<'b> {
+    Send { flow: Flow, transmit: Transmit<'b> },
+    // Poll again once the duration has elapsed
+    Wait(Duration),
+    Done(Duration),
+    Failed(Failure),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum State {
+    Refresh,
+    // The mapping learnt from the refresh, to be probed once the wait is over
+    Wait(SocketAddr),
+    Probe(SocketAddr),
+    Done(Duration),
+    Failed(Failure),
+}
+
+// https://datatracker.ietf.org/doc/html/rfc5780#section-4.6
+// Each round refreshes the mapping over the primary flow, waits, and then probes it over the
+// secondary flow. The wait doubles until a probe goes unanswered, then the lifetime is binary
+// searched down to the resolution.
+#[derive(Debug, Clone)]
+pub struct LifetimeDiscovery {
+    server: SocketAddr,
+    state: State,
+    transaction_id: Option<TransactionId>,
+    current: Duration,
+    lower: Duration,
+    upper: Option<Duration>,
+    max: Duration,
+    resolution: Duration,
+}
+
+impl LifetimeDiscovery {
+    // The initial wait and the resolution are raised to this, otherwise the search never ends
+    pub const MIN_STEP: Duration = Duration::from_secs(1);
+
+    #[inline]
+    pub fn new(server: SocketAddr, initial: Duration, max: Duration, resolution: Duration) -> Self {
+        Self {
+            server,
+            state: State::Refresh,
+            transaction_id: None,
+            current: initial.max(Self::MIN_STEP).min(max),
+            lower: Duration::ZERO,
+            upper: None,
+            max,
+            resolution: resolution.max(Self::MIN_STEP),
+        }
+    }
+
+    // Encodes the next request, a new transaction id replaces the previous one
+    pub fn poll<'b, B: Buffer + ?Sized>(
+        &mut self,
+        transaction_id: TransactionId,
+        buf: &'b mut B,
+    ) -> Result<LifetimeStep<'b>, StunError> {
+        let (flow, attr) = match self.state {
+            State::Refresh => (Flow::Primary, None),
+            State::Probe(mapped) => (Flow::Secondary, Some(ResponsePort::new(mapped.port()))),
+            State::Wait(mapped) => {
+                self.state = State::Probe(mapped);
+                return Ok(LifetimeStep::Wait(self.current));
+            }
+            State::Done(lifetime) => return Ok(LifetimeStep::Done(lifetime)),
+            State::Failed(failure) => return Ok(LifetimeStep::Failed(failure)),
+        };
+        let attr = attr.as_ref().map(|attr| attr as _);
+        let message = super::binding_request(transaction_id, buf, attr)?;
+        self.transaction_id = Some(transaction_id);
+        let transmit = Transmit { destination: self.server, message };
+        Ok(LifetimeStep::Send { flow, transmit })
+    }
+
+    // Responses to anything but the outstanding request are ignored
+    pub fn handle_response(&mut self, msg: &MessageParser) -> Result<(), StunError> {
+        if self.transaction_id != Some(*msg.transaction_id()) {
+            return Ok(());
+        }
+        match (self.state, msg.class()) {
+            (_, Class::ErrorResponse) => self.state = State::Failed(Failure::ErrorResponse),
+            (State::Refresh, Class::SuccessResponse) => {
+                self.state = State::Wait(super::mapped_address(msg)?);
+            }
+            (State::Probe(_), Class::SuccessResponse) => {
+                self.lower = self.current;
+                self.next_round();
+            }
+            _ => return Ok(()),
+        }
+        self.transaction_id = None;
+        Ok(())
+    }
+
+    // The outstanding request went unanswered after all retransmissions
+    pub fn handle_timeout(&mut self) {
+        if self.transaction_id.take().is_none() {
+            return;
+        }
+        match self.state {
+            State::Refresh => self.state = State::Failed(Failure::NoResponse),
+            State::Probe(_) => {
+                self.upper = Some(self.current);
+                self.next_round();
+            }
+            _ => {}
+        }
+    }
+
+    // The longest wait the mapping survived so far, and the shortest it didn't
+    #[inline]
+    pub fn bounds(&self) -> (Duration, Option<Duration>) {
+        (self.lower, self.upper)
+    }
+
+    fn next_round(&mut self) {
+        self.state = match self.upper {
+            Some(upper) if upper.saturating_sub(self.lower) <= self.resolution => {
+                State::Done(self.lower)
+            }
+            Some(upper) => {
+                self.current = self.lower + (upper - self.lower) / 2;
+                State::Refresh
+            }
+            None if self.lower >= self.max => State::Done(self.lower),
+            None => {
+                self.current = self.current.saturating_mul(2).min(self.max);
+                State::Refresh
+            }
+        };
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        attribute::{rfc8489::XorMappedAddress, Type},
+        build::MessageBuilder,
+        test_data::assert_ok,
+        Method,
+    };
+    use core::mem::MaybeUninit;
+
+    // A NAT dropping mappings idle for longer than the lifetime
+    fn run(lifetime: Duration, initial: Duration, max: Duration, resolution: Duration) -> Duration {
+        let server = "192.0.2.1:3478".parse().unwrap();
+        let mapped: SocketAddr = "203.0.113.1:40000".parse().unwrap();
+        let mut discovery = LifetimeDiscovery::new(server, initial, max, resolution);
+        let (mut req, mut res, mut waited) = ([0; 64], [0; 64], Duration::ZERO);
+        for i in 0.. {
+            let id = TransactionId::new([i as u8; 12]);
+            let (flow, transmit) = match assert_ok!(discovery.poll(id, &mut req), "bad poll") {
+                LifetimeStep::Send { flow, transmit } => (flow, transmit),
+                LifetimeStep::Wait(duration) => {
+                    waited = duration;
+                    continue;
+                }
+                LifetimeStep::Done(lifetime) => return lifetime,
+                LifetimeStep::Failed(failure) => panic!("discovery failed: {failure:?}"),
+            };
+            let mut v = [MaybeUninit::uninit(); 4];
+            let msg = assert_ok!(
+                MessageParser::from_complete_message(transmit.message, &mut v),
+                "bad msg",
+            );
+            let port = msg.find::<ResponsePort>(Type::RESPONSE_PORT).unwrap();
+            assert_eq!(port.is_some(), flow == Flow::Secondary);
+            let dropped =
+                port.is_some_and(|port| port.port() != mapped.port() || waited > lifetime);
+            if dropped {
+                discovery.handle_timeout();
+                continue;
+            }
+            let mut builder = assert_ok!(
+                MessageBuilder::new(Class::SuccessResponse, Method::BINDING, id, &mut res),
+                "bad builder",
+            );
+            assert_ok!(builder.add(&XorMappedAddress::new(mapped)), "bad attr");
+            let msg = assert_ok!(
+                MessageParser::from_complete_message(builder.finish(), &mut v),
+                "bad msg",
+            );
+            assert_ok!(discovery.handle_response(&msg), "bad response");
+        }
+        unreachable!()
+    }
+
+    #[test]
+    fn test_lifetime() {
+        let (max, second) = (Duration::from_secs(3600), Duration::from_secs(1));
+        for secs in [0, 5, 10, 29, 30, 31, 120, 299] {
+            let lifetime = run(Duration::from_secs(secs), 10 * second, max, second);
+            assert!(lifetime <= Duration::from_secs(secs), "{lifetime:?} > {secs}");
+            assert!(lifetime + second >= Duration::from_secs(secs), "{lifetime:?} < {secs}");
+            // zero steps are raised to a second instead of searching forever
+            let lifetime = run(Duration::from_secs(secs), Duration::ZERO, max, Duration::ZERO);
+            assert!(lifetime <= Duration::from_secs(secs), "{lifetime:?} > {secs}");
+            assert!(lifetime + second >= Duration::from_secs(secs), "{lifetime:?} < {secs}");
+        }
+        assert_eq!(run(Duration::MAX, 10 * second, max, second), max);
+        assert_eq!(run(Duration::MAX, Duration::ZERO, Duration::ZERO, second), Duration::ZERO);
+    }
+}
@@ -0,0 +1,47 @@
+use crate::{
+    attribute::{rfc8489::XorMappedAddress, EncodeAttribute, Type},
+    build::{Buffer, MessageBuilder},
+    error::{new_error, StunError},
+    header::{Class, Method, TransactionId},
+    net::SocketAddr,
+    parse::MessageParser,
+};
+
+mod behavior;
+mod lifetime;
+
+pub use behavior::{
+    BehaviorDiscovery, Failure, FilteringBehavior, MappingBehavior, NatBehavior, Progress,
+};
+pub use lifetime::{Flow, LifetimeDiscovery, LifetimeStep};
+
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub struct Transmit<'b> {
+    pub destination: SocketAddr,
+    pub message: &'b mut [u8],
+}
+
+fn binding_request<'b, B: Buffer + ?Sized>(
+    transaction_id: TransactionId,
+    buf: &'b mut B,
+    attr: Option<&dyn EncodeAttribute>,
+) -> Result<&'b mut [u8], StunError> {
+    let mut builder = MessageBuilder::new(Class::Request, Method::BINDING, transaction_id, buf)?;
+    if let Some(attr) = attr {
+        builder.add(attr)?;
+    }
+    Ok(builder.finish())
+}
+
+fn mapped_address(msg: &MessageParser) -> Result<SocketAddr, StunError> {
+    match msg.find::<XorMappedAddress>(Type::XOR_MAPPED_ADDRESS)? {
+        Some(addr) => Ok(*addr.addr()),
+        None => Err(MissingAttribute::new(Type::XOR_MAPPED_ADDRESS).into()),
+    }
+}
+
+new_error!(
+    MissingAttribute { attr: Type },
+    InvalidParameter,
+    "the response is missing the {attr} attribute",
+);
@@ -1,8 +1,14 @@
 use crate::{
-    attribute::{DecodeAttribute, Type},
+    attribute::{
+        rfc8489::{Fingerprint, UnknownAttributes},
+        DecodeAttribute, Type, UnderstoodAttributes,
+    },
     error::{new_error, StunError},
-    header::{Class, Header, Method, TransactionId},
-    parse::attribute::{Container, RawAttribute},
+    header::{self, Class, Header, Method, TransactionId},
+    parse::{
+        attribute::{Container, RawAttribute},
+        AttributeOrdering, ParseOptions,
+    },
     util, MAGIC_COOKIE,
 };
 use core::{iter::Map, slice::Iter as SliceIter};
@@ -10,15 +16,27 @@ use core::{iter::Map, slice::Iter as SliceIter};
 pub struct MessageParser<'src, 'attr> {
     class: Class,
     method: Method,
+    magic_cookie: u32,
     transaction_id: TransactionId,
     src: &'src [u8],
     attrs: &'attr [RawAttribute],
+    ordering: AttributeOrdering,
+    ignored: u16,
 }
 
 impl<'src, 'attr> MessageParser<'src, 'attr> {
+    #[inline]
     pub fn from_complete_message<C: Container + ?Sized>(
         src: &'src [u8],
         attrs: &'attr mut C,
+    ) -> Result<Self, StunError> {
+        Self::from_complete_message_with(src, attrs, ParseOptions::new())
+    }
+
+    pub fn from_complete_message_with<C: Container + ?Sized>(
+        src: &'src [u8],
+        attrs: &'attr mut C,
+        options: ParseOptions,
     ) -> Result<Self, StunError> {
         if src.len() < Header::LEN {
             new_error!(
@@ -30,15 +48,25 @@ impl<'src, 'attr> MessageParser<'src, 'attr> {
         }
         let (header, src) = util::split_array_ref(src);
         let header = Header::decode(header)?;
-        Self::from_header_and_attrs(header, src, attrs)
+        Self::from_header_and_attrs_with(header, src, attrs, options)
     }
 
+    #[inline]
     pub fn from_header_and_attrs<C: Container + ?Sized>(
         header: Header,
         src: &'src [u8],
         attrs: &'attr mut C,
     ) -> Result<Self, StunError> {
-        if header.is_rfc3489() {
+        Self::from_header_and_attrs_with(header, src, attrs, ParseOptions::new())
+    }
+
+    pub fn from_header_and_attrs_with<C: Container + ?Sized>(
+        header: Header,
+        src: &'src [u8],
+        attrs: &'attr mut C,
+        options: ParseOptions,
+    ) -> Result<Self, StunError> {
+        if header.is_rfc3489() && !options.rfc3489() {
             new_error!(
                 MagicCookie { cookie: u32 },
                 MagicCookie,
@@ -56,8 +84,13 @@ impl<'src, 'attr> MessageParser<'src, 'attr> {
             let len = src.len().try_into().unwrap_or(u16::MAX);
             return Err(BufferLenMismatch::new(header.length, len).into());
         }
-        let Header { class, method, transaction_id, .. } = header;
-        attrs.parse(src).map(|attrs| Self { class, method, transaction_id, src, attrs })
+        let Header { class, method, magic_cookie, transaction_id, .. } = header;
+        let attrs = attrs.parse(src)?;
+        let ordering = options.ordering();
+        let len = ordering.apply(attrs)?;
+        let ignored = (attrs.len() - len) as u16;
+        let attrs = &attrs[..len];
+        Ok(Self { class, method, magic_cookie, transaction_id, src, attrs, ordering, ignored })
     }
 
     #[inline]
@@ -92,6 +125,13 @@ impl<'src, 'attr> MessageParser<'src, 'attr> {
         })
     }
 
+    // Decodes the first attribute of the given type
+    #[inline]
+    pub fn find<D: DecodeAttribute<'src>>(&self, attr: Type) -> Result<Option<D>, StunError> {
+        let item = self.iter_raw().find(|item| item.attr == attr);
+        item.map(|item| D::decode(item.attr, item.value, &self.transaction_id)).transpose()
+    }
+
     #[inline]
     pub fn class(&self) -> Class {
         self.class
@@ -107,58 +147,74 @@ impl<'src, 'attr> MessageParser<'src, 'attr> {
         &self.transaction_id
     }
 
+    #[inline]
+    pub fn is_rfc3489(&self) -> bool {
+        self.magic_cookie != MAGIC_COOKIE
+    }
+
+    #[inline]
+    pub fn rfc3489_transaction_id(&self) -> [u8; 16] {
+        header::rfc3489_transaction_id(self.magic_cookie, &self.transaction_id)
+    }
+
+    #[inline]
+    pub fn ordering(&self) -> AttributeOrdering {
+        self.ordering
+    }
+
+    // The number of attributes trimmed for following MESSAGE-INTEGRITY or FINGERPRINT
+    #[inline]
+    pub fn ignored(&self) -> usize {
+        self.ignored as usize
+    }
+
     #[inline]
     pub fn validation_header(&self) -> ValidationHeader {
-        let &Self { class, method, transaction_id, .. } = self;
-        let (magic_cookie, length) = (MAGIC_COOKIE, self.src.len() as _);
-        let mut validation_header = ValidationHeader([0; Header::LEN]);
-        Header { class, method, transaction_id, magic_cookie, length }
-            .encode(&mut validation_header.0);
-        validation_header
+        let &Self { class, method, magic_cookie, transaction_id, .. } = self;
+        let length = self.src.len() as _;
+        ValidationHeader::new(&Header { class, method, transaction_id, magic_cookie, length })
     }
 
-    // pub fn validate(&mut self) -> Result<Validation, ValidationError> {
-    //     if self.header.is_rfc3489() {
-    //         return Err(ValidationError::MagicCookie);
-    //     }
-    //     let mut validation = Validation { fingerprint: None };
-    //     let mut iter = self.attrs.iter();
-    //     let Some(last) = iter.next_back() else {
-    //         return Ok(validation);
-    //     };
-    //     struct HeaderCustom<'a> {
-    //         class_method: [u8; 2],
-    //         transaction_id: &'a TransactionId,
-    //     }
-    //
-    //     impl<'a> HeaderCustom<'a> {
-    //         fn from_header(header: &'a Header) -> Self {
-    //             Self { class_method: header.class_method(), transaction_id: &header.transaction_id }
-    //         }
-    //
-    //         fn with_length(&self, length: u16, mut f: impl FnMut(&[u8])) {
-    //             f(&self.class_method);
-    //             f(&length.to_be_bytes());
-    //             f(&MAGIC_COOKIE_BYTES);
-    //             f(&self.transaction_id.0);
-    //         }
-    //     }
-    //
-    //     let header = HeaderCustom::from_header(&self.header);
-    //     if last.typ == AttributeType::FINGERPRINT {
-    //         let mut digest = Fingerprint::DIGEST;
-    //         let fingerprint = last.val.try_into().map_err(|_| ValidationError::Fingerprint)?;
-    //         let fingerprint = validation.fingerprint.insert(fingerprint);
-    //         header.with_length(self.header.length, |b| digest.update(b));
-    //         digest.update(Self::slice_up_to(self.src, last.val));
-    //         let crc = digest.finalize() ^ Fingerprint::XOR;
-    //         if crc != fingerprint.crc32() {
-    //             return Err(ValidationError::Fingerprint);
-    //         }
-    //         self.attrs = iter.as_slice();
-    //     }
-    //     Ok(validation)
-    // }
+    // https://datatracker.ietf.org/doc/html/rfc8489#section-6.3.1
+    // Collects the distinct comprehension-required attributes that aren't understood, for a 420
+    // response, dropping any that don't fit in `unknown`.
+    pub fn check_comprehension<'u, U: UnderstoodAttributes + ?Sized>(
+        &self,
+        understood: &U,
+        unknown: &'u mut [Type],
+    ) -> Option<UnknownAttributes<&'u [Type]>> {
+        let (mut len, mut found) = (0, false);
+        for attr in self.attrs.iter().map(|attr| attr.attr) {
+            if !attr.is_comprehension_required() || understood.understands(attr) {
+                continue;
+            }
+            found = true;
+            if len < unknown.len() && !unknown[..len].contains(&attr) {
+                unknown[len] = attr;
+                len += 1;
+            }
+        }
+        found.then(|| UnknownAttributes::new(&unknown[..len]))
+    }
+
+    pub fn verify_fingerprint(&self) -> Result<Option<Fingerprint>, StunError> {
+        let mut iter = self.iter_raw();
+        let Some(item) = iter.find(|item| item.attr == Fingerprint::TYPE) else {
+            return Ok(None);
+        };
+        // https://datatracker.ietf.org/doc/html/rfc8489#section-14.7
+        if let Some(next) = iter.next() {
+            new_error!(
+                FingerprintNotLast { attr: Type },
+                InvalidParameter,
+                "FINGERPRINT must be the last attribute, but it is followed by {attr}",
+            );
+            return Err(FingerprintNotLast::new(next.attr).into());
+        }
+        let fingerprint = Fingerprint::decode(item.attr, item.value, self.transaction_id())?;
+        fingerprint.verify(self.validation_header(), &item)?;
+        Ok(Some(fingerprint))
+    }
 }
 
 pub struct Item<'src> {
@@ -192,7 +248,7 @@ pub type Convert<'src, 'attr, D: DecodeAttribute<'src>> =
     impl FnMut(&'attr RawAttribute) -> Result<D, StunError> + 'src;
 
 #[inline]
-fn convert(src: &[u8], RawAttribute { attr, len, off, .. }: RawAttribute) -> Item {
+fn convert(src: &[u8], RawAttribute { attr, len, off, .. }: RawAttribute) -> Item<'_> {
     let (offset, len) = (off as usize, len as usize);
     let (value, attrs_up_to) = unsafe { (value(src, offset, len), attrs_up_to(src, offset)) };
     Item { attr, value, attrs_up_to }
@@ -209,6 +265,13 @@ unsafe fn value(src: &[u8], offset: usize, len: usize) -> &[u8] {
 pub struct ValidationHeader([u8; Header::LEN]);
 
 impl ValidationHeader {
+    #[inline]
+    pub fn new(header: &Header) -> Self {
+        let mut validation_header = Self([0; Header::LEN]);
+        header.encode(&mut validation_header.0);
+        validation_header
+    }
+
     pub fn as_bytes(&self) -> &[u8; Header::LEN] {
         &self.0
     }
@@ -225,17 +288,6 @@ impl ValidationHeader {
     }
 }
 
-// pub struct Validation {
-//     fingerprint: Option<Fingerprint>,
-// }
-//
-// #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-// pub enum ValidationError {
-//     Fingerprint,
-//     MessageIntegrity,
-//     MagicCookie,
-// }
-
 #[cfg(test)]
 mod test {
     use super::*;
@@ -275,4 +327,159 @@ mod test {
             }
         }
     }
+
+    #[test]
+    fn test_verify_fingerprint() {
+        let mut v = [core::mem::MaybeUninit::uninit(); 32];
+        for (i, parts) in TEST_VECTOR.iter().enumerate() {
+            let msg = assert_ok!(
+                MessageParser::from_complete_message(parts.message, &mut v),
+                "error parsing raw msg",
+            );
+            let fingerprint = assert_ok!(msg.verify_fingerprint(), "error verifying fingerprint");
+            assert_eq!(fingerprint.is_some(), i < 3);
+        }
+        let mut message = SAMPLE_IPV4_RESPONSE;
+        message[30] ^= 1;
+        let msg = assert_ok!(
+            MessageParser::from_complete_message(&message, &mut v),
+            "error parsing raw msg",
+        );
+        let err = msg.verify_fingerprint().unwrap_err();
+        assert_eq!(err.error_kind(), crate::error::StunErrorKind::ValidationFailed);
+    }
+
+    #[test]
+    fn test_check_comprehension() {
+        let (mut v, mut unknown) = ([core::mem::MaybeUninit::uninit(); 32], [Type::new(0); 4]);
+        let msg = assert_ok!(
+            MessageParser::from_complete_message(&SAMPLE_REQUEST_LONG_TERM_AUTH, &mut v),
+            "error parsing raw msg",
+        );
+        let all = [Type::USERNAME, Type::NONCE, Type::REALM, Type::MESSAGE_INTEGRITY];
+        assert!(msg.check_comprehension(&all, &mut unknown).is_none());
+        assert!(msg.check_comprehension(&|_| true, &mut unknown).is_none());
+        let attrs = msg.check_comprehension(&all[..1], &mut unknown).expect("unknown attributes");
+        assert_eq!(attrs.into_inner(), &all[1..]);
+        let attrs =
+            msg.check_comprehension(&all[..1], &mut unknown[..1]).expect("unknown attributes");
+        assert_eq!(attrs.into_inner(), &all[1..2]);
+        // SOFTWARE is comprehension-optional
+        let msg = assert_ok!(
+            MessageParser::from_complete_message(&SAMPLE_IPV4_RESPONSE, &mut v),
+            "error parsing raw msg",
+        );
+        assert!(msg.check_comprehension(&|attr| attr != Type::SOFTWARE, &mut unknown).is_none());
+    }
+
+    #[test]
+    fn test_rfc3489() {
+        use crate::{
+            attribute::rfc3489::{parsed::ClassicAttribute, *},
+            build::MessageBuilder,
+        };
+
+        let (mut v, mut arr) = ([core::mem::MaybeUninit::uninit(); 32], [0; 128]);
+        let id = *b"\x01\x02\x03\x04classic-3489";
+        let header = Header::new_rfc3489(Class::SuccessResponse, Method::BINDING, 0, id);
+        let mut builder = assert_ok!(MessageBuilder::from_header(header, &mut arr), "bad builder");
+        let attrs = [
+            ClassicAttribute::from(MappedAddress::new("192.0.2.1:32853".parse().unwrap())),
+            SourceAddress::new("192.0.2.2:3478".parse().unwrap()).into(),
+            ChangedAddress::new("192.0.2.3:3479".parse().unwrap()).into(),
+            ReflectedFrom::new("[2001:db8::1]:5000".parse().unwrap()).into(),
+            Password::new(&b"pass"[..]).unwrap().into(),
+        ];
+        for attr in &attrs {
+            assert_ok!(builder.add(attr), "error adding attribute");
+        }
+        let message = builder.finish();
+        // no xor, the address is encoded as is
+        assert_eq!(&message[Header::LEN + 8..Header::LEN + 12], &[192, 0, 2, 1]);
+        let err = MessageParser::from_complete_message(message, &mut v).map(|_| ()).unwrap_err();
+        assert_eq!(err.error_kind(), crate::error::StunErrorKind::MagicCookie);
+        let msg = assert_ok!(
+            MessageParser::from_complete_message_with(
+                message,
+                &mut v,
+                ParseOptions::new().with_rfc3489(true),
+            ),
+            "error parsing classic msg",
+        );
+        assert!(msg.is_rfc3489());
+        assert_eq!(msg.rfc3489_transaction_id(), id);
+        assert!(msg.iter::<ClassicAttribute>().map(Result::unwrap).eq(attrs));
+        let err = Password::new(&b"pas"[..]).unwrap_err();
+        assert_eq!(err.error_kind(), crate::error::StunErrorKind::InvalidParameter);
+        let msg = assert_ok!(
+            MessageParser::from_complete_message_with(
+                &SAMPLE_REQUEST,
+                &mut v,
+                ParseOptions::new().with_rfc3489(true),
+            ),
+            "error parsing raw msg",
+        );
+        assert!(!msg.is_rfc3489());
+    }
+
+    #[test]
+    fn test_ordering() {
+        let mut v = [core::mem::MaybeUninit::uninit(); 32];
+        for parts in TEST_VECTOR {
+            let msg = assert_ok!(
+                MessageParser::from_complete_message_with(
+                    parts.message,
+                    &mut v,
+                    ParseOptions::new().with_ordering(AttributeOrdering::Reject),
+                ),
+                "error parsing raw msg",
+            );
+            assert_eq!(msg.ignored(), 0);
+            assert_eq!(msg.ordering(), AttributeOrdering::Reject);
+        }
+        // SOFTWARE, MESSAGE-INTEGRITY, USERNAME, FINGERPRINT, SOFTWARE
+        let mut message = [0u8; Header::LEN + 5 * 8];
+        let header = Header::new(Class::Request, Method::BINDING, 40, TransactionId::new([0; 12]));
+        header.encode(util::split_array_mut(&mut message).0);
+        let attrs = [
+            Type::SOFTWARE,
+            Type::MESSAGE_INTEGRITY,
+            Type::USERNAME,
+            Type::FINGERPRINT,
+            Type::SOFTWARE,
+        ];
+        for (chunk, attr) in message[Header::LEN..].chunks_exact_mut(8).zip(attrs) {
+            RawAttribute::encode_type_length(attr, 4, util::split_array_mut(chunk).0);
+        }
+        let msg = assert_ok!(
+            MessageParser::from_complete_message(&message, &mut v),
+            "error parsing raw msg",
+        );
+        assert_eq!(msg.ordering(), AttributeOrdering::Trim);
+        assert_eq!(msg.ignored(), 2);
+        let parsed: [_; 3] = core::array::from_fn(|i| msg.attrs[i].attr);
+        assert_eq!(parsed, [Type::SOFTWARE, Type::MESSAGE_INTEGRITY, Type::FINGERPRINT]);
+        assert_eq!(msg.iter_raw().next_back().unwrap().attrs_up_to().len(), 24);
+        let msg = assert_ok!(
+            MessageParser::from_complete_message_with(
+                &message,
+                &mut v,
+                ParseOptions::new().with_ordering(AttributeOrdering::Keep),
+            ),
+            "error parsing raw msg",
+        );
+        assert_eq!(msg.ordering(), AttributeOrdering::Keep);
+        assert_eq!(msg.ignored(), 0);
+        assert_eq!(msg.iter_raw().count(), attrs.len());
+        let err = msg.verify_fingerprint().unwrap_err();
+        assert_eq!(err.error_kind(), crate::error::StunErrorKind::InvalidParameter);
+        let err = MessageParser::from_complete_message_with(
+            &message,
+            &mut v,
+            ParseOptions::new().with_ordering(AttributeOrdering::Reject),
+        )
+        .map(|_| ())
+        .unwrap_err();
+        assert_eq!(err.error_kind(), crate::error::StunErrorKind::InvalidParameter);
+    }
 }
@@ -1,5 +1,7 @@
 mod attribute;
 mod message;
+mod options;
 
 pub use attribute::RawAttribute;
 pub use message::{Item, MessageParser, ValidationHeader};
+pub use options::{AttributeOrdering, ParseOptions};
@@ -0,0 +1,95 @@
+use crate::{
+    attribute::Type,
+    error::{new_error, StunError},
+    parse::RawAttribute,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct ParseOptions {
+    ordering: AttributeOrdering,
+    rfc3489: bool,
+}
+
+impl ParseOptions {
+    #[inline]
+    pub const fn new() -> Self {
+        Self { ordering: AttributeOrdering::Trim, rfc3489: false }
+    }
+
+    #[inline]
+    pub const fn with_ordering(mut self, ordering: AttributeOrdering) -> Self {
+        self.ordering = ordering;
+        self
+    }
+
+    #[inline]
+    pub const fn ordering(&self) -> AttributeOrdering {
+        self.ordering
+    }
+
+    // Accept messages without the magic cookie, treating it as part of a 16 byte transaction id
+    #[inline]
+    pub const fn with_rfc3489(mut self, rfc3489: bool) -> Self {
+        self.rfc3489 = rfc3489;
+        self
+    }
+
+    #[inline]
+    pub const fn rfc3489(&self) -> bool {
+        self.rfc3489
+    }
+}
+
+// https://datatracker.ietf.org/doc/html/rfc8489#section-14.5
+// Only MESSAGE-INTEGRITY-SHA256 and FINGERPRINT may follow MESSAGE-INTEGRITY, only FINGERPRINT may
+// follow MESSAGE-INTEGRITY-SHA256, and nothing may follow FINGERPRINT.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum AttributeOrdering {
+    // Drop the out of order attributes, as the RFC requires of agents
+    #[default]
+    Trim,
+    // Keep every attribute in the order it was received, the unauthenticated ones included
+    Keep,
+    // Fail parsing on the first out of order attribute
+    Reject,
+}
+
+impl AttributeOrdering {
+    // Compacts the allowed attributes to the front, returning how many there are.
+    pub(crate) fn apply(self, attrs: &mut [RawAttribute]) -> Result<usize, StunError> {
+        if self == Self::Keep {
+            return Ok(attrs.len());
+        }
+        let (mut len, mut last) = (0, None);
+        for i in 0..attrs.len() {
+            let attr = attrs[i].attr;
+            match (last, attr) {
+                (None, _)
+                | (
+                    Some(Type::MESSAGE_INTEGRITY),
+                    Type::MESSAGE_INTEGRITY_SHA256 | Type::FINGERPRINT,
+                )
+                | (Some(Type::MESSAGE_INTEGRITY_SHA256), Type::FINGERPRINT) => {}
+                (Some(after), _) if self == Self::Reject => {
+                    return Err(AttributeAfterIntegrity::new(attr, after).into())
+                }
+                (Some(_), _) => continue,
+            }
+            if matches!(
+                attr,
+                Type::MESSAGE_INTEGRITY | Type::MESSAGE_INTEGRITY_SHA256 | Type::FINGERPRINT
+            ) {
+                last = Some(attr);
+            }
+            attrs[len] = attrs[i];
+            len += 1;
+        }
+        Ok(len)
+    }
+}
+
+new_error!(
+    AttributeAfterIntegrity { attr: Type, after: Type },
+    InvalidParameter,
+    "attribute {attr} is not allowed after {after}",
+);
@@ -0,0 +1,366 @@
+use crate::{
+    attribute::{
+        rfc3489::parsed::ClassicAttribute,
+        rfc8489::{ErrorCode, MappedAddress, Software, XorMappedAddress},
+        EncodeAttribute, Type, UnderstoodAttributes,
+    },
+    build::{Buffer, MessageBuilder},
+    error::StunError,
+    header::{Class, Header, Method},
+    net::SocketAddr,
+    parse::{AttributeOrdering, MessageParser, ParseOptions, RawAttribute},
+    util,
+};
+use core::mem::MaybeUninit;
+
+const MAX_ATTRIBUTES: usize = 32;
+const MAX_UNKNOWN_ATTRIBUTES: usize = 16;
+
+// https://datatracker.ietf.org/doc/html/rfc8489#section-18.3
+// The RFC 8489 attributes, along with the ones ICE connectivity checks carry
+const UNDERSTOOD: [Type; 20] = [
+    Type::MAPPED_ADDRESS,
+    Type::USERNAME,
+    Type::MESSAGE_INTEGRITY,
+    Type::ERROR_CODE,
+    Type::UNKNOWN_ATTRIBUTES,
+    Type::REALM,
+    Type::NONCE,
+    Type::MESSAGE_INTEGRITY_SHA256,
+    Type::PASSWORD_ALGORITHM,
+    Type::USERHASH,
+    Type::XOR_MAPPED_ADDRESS,
+    Type::PASSWORD_ALGORITHMS,
+    Type::ALTERNATE_DOMAIN,
+    Type::SOFTWARE,
+    Type::ALTERNATE_SERVER,
+    Type::FINGERPRINT,
+    // https://datatracker.ietf.org/doc/html/rfc8445#section-16.1
+    Type::PRIORITY,
+    Type::USE_CANDIDATE,
+    Type::ICE_CONTROLLED,
+    Type::ICE_CONTROLLING,
+];
+
+// https://datatracker.ietf.org/doc/html/rfc8489#section-6.3
+// Answers Binding requests with the reflexive transport address of their source. Messages that
+// aren't STUN requests are discarded, while malformed requests and requests with unknown
+// comprehension-required attributes are answered with 400 and 420 error responses.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BindingServer<S = &'static str, U = fn(Type) -> bool> {
+    software: Option<Software<S>>,
+    understood: Option<U>,
+    fingerprint: bool,
+    rfc3489: bool,
+}
+
+impl BindingServer {
+    #[inline]
+    pub const fn new() -> Self {
+        Self { software: None, understood: None, fingerprint: false, rfc3489: false }
+    }
+}
+
+impl Default for BindingServer {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S: AsRef<str>, U: UnderstoodAttributes> BindingServer<S, U> {
+    pub const MAX_ATTRIBUTES: usize = MAX_ATTRIBUTES;
+
+    pub const MAX_UNKNOWN_ATTRIBUTES: usize = MAX_UNKNOWN_ATTRIBUTES;
+
+    pub const UNDERSTOOD: &'static [Type] = &UNDERSTOOD;
+
+    #[inline]
+    pub fn with_software<T: AsRef<str>>(self, software: Software<T>) -> BindingServer<T, U> {
+        let Self { understood, fingerprint, rfc3489, .. } = self;
+        BindingServer { software: Some(software), understood, fingerprint, rfc3489 }
+    }
+
+    // Replaces the default of `UNDERSTOOD`, and the RFC 3489 attributes in RFC 3489 mode
+    #[inline]
+    pub fn with_understood<V: UnderstoodAttributes>(self, understood: V) -> BindingServer<S, V> {
+        let Self { software, fingerprint, rfc3489, .. } = self;
+        BindingServer { software, understood: Some(understood), fingerprint, rfc3489 }
+    }
+
+    #[inline]
+    pub fn with_fingerprint(mut self, fingerprint: bool) -> Self {
+        self.fingerprint = fingerprint;
+        self
+    }
+
+    // RFC 3489 clients are answered with MAPPED-ADDRESS, as they'd discard a response with the
+    // comprehension-required XOR-MAPPED-ADDRESS they don't understand.
+    #[inline]
+    pub fn with_rfc3489(mut self, rfc3489: bool) -> Self {
+        self.rfc3489 = rfc3489;
+        self
+    }
+
+    #[inline]
+    pub fn software(&self) -> Option<&Software<S>> {
+        self.software.as_ref()
+    }
+
+    #[inline]
+    pub fn understands(&self, attr: Type) -> bool {
+        match &self.understood {
+            Some(understood) => understood.understands(attr),
+            None => {
+                UNDERSTOOD.contains(&attr)
+                    || self.rfc3489 && ClassicAttribute::TYPES.contains(&attr)
+            }
+        }
+    }
+
+    #[inline]
+    pub fn fingerprint(&self) -> bool {
+        self.fingerprint
+    }
+
+    #[inline]
+    pub fn rfc3489(&self) -> bool {
+        self.rfc3489
+    }
+
+    // Writes the response to the datagram received from `source` into `buf`, returning None if
+    // the datagram is to be silently discarded.
+    pub fn handle<'b, B: Buffer + ?Sized>(
+        &self,
+        src: &[u8],
+        source: SocketAddr,
+        buf: &'b mut B,
+    ) -> Result<Option<&'b mut [u8]>, StunError> {
+        let Some(header) = self.decode_header(src) else {
+            return Ok(None);
+        };
+        if header.class != Class::Request {
+            return Ok(None);
+        }
+        let mut attrs = [MaybeUninit::<RawAttribute>::uninit(); MAX_ATTRIBUTES];
+        let options =
+            ParseOptions::new().with_ordering(AttributeOrdering::Trim).with_rfc3489(self.rfc3489);
+        let Ok(msg) = MessageParser::from_header_and_attrs_with(
+            header,
+            &src[Header::LEN..],
+            &mut attrs,
+            options,
+        ) else {
+            return self.error_response(header, &ErrorCode::BAD_REQUEST, buf).map(Some);
+        };
+        if msg.verify_fingerprint().is_err() {
+            return Ok(None);
+        }
+        let mut unknown = [Type::new(0); MAX_UNKNOWN_ATTRIBUTES];
+        if let Some(unknown) = msg.check_comprehension(&|attr| self.understands(attr), &mut unknown)
+        {
+            let mut builder = self.builder(header, Class::ErrorResponse, buf)?;
+            builder.add(&ErrorCode::UNKNOWN_ATTRIBUTE)?.add(&unknown)?;
+            return self.finish(builder).map(Some);
+        }
+        if header.method != Method::BINDING {
+            return self.error_response(header, &ErrorCode::BAD_REQUEST, buf).map(Some);
+        }
+        let mut builder = self.builder(header, Class::SuccessResponse, buf)?;
+        match header.is_rfc3489() {
+            true => builder.add(&MappedAddress::new(source))?,
+            false => builder.add(&XorMappedAddress::new(source))?,
+        };
+        self.finish(builder).map(Some)
+    }
+
+    fn decode_header(&self, src: &[u8]) -> Option<Header> {
+        let header = Header::decode(util::split_array_ref(src.get(..Header::LEN)?).0).ok()?;
+        let valid = header.length as usize == src.len() - Header::LEN
+            && (self.rfc3489 || !header.is_rfc3489());
+        valid.then_some(header)
+    }
+
+    fn error_response<'b, B: Buffer + ?Sized>(
+        &self,
+        header: Header,
+        code: &dyn EncodeAttribute,
+        buf: &'b mut B,
+    ) -> Result<&'b mut [u8], StunError> {
+        let mut builder = self.builder(header, Class::ErrorResponse, buf)?;
+        builder.add(code)?;
+        self.finish(builder)
+    }
+
+    #[inline]
+    fn builder<'b, B: Buffer + ?Sized>(
+        &self,
+        request: Header,
+        class: Class,
+        buf: &'b mut B,
+    ) -> Result<MessageBuilder<'b, B>, StunError> {
+        // the transaction id, along with the magic cookie of RFC 3489 requests, is echoed back
+        let mut builder = MessageBuilder::from_header(Header { class, ..request }, buf)?;
+        if let Some(software) = &self.software {
+            builder.add(software)?;
+        }
+        Ok(builder)
+    }
+
+    #[inline]
+    fn finish<'b, B: Buffer + ?Sized>(
+        &self,
+        mut builder: MessageBuilder<'b, B>,
+    ) -> Result<&'b mut [u8], StunError> {
+        if self.fingerprint {
+            builder.add_fingerprint()?;
+        }
+        Ok(builder.finish())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        attribute::{
+            rfc5780::ChangeRequest,
+            rfc8445::{Priority, UseCandidate},
+            rfc8489::parsed,
+            UnknownAttribute,
+        },
+        header::TransactionId,
+        test_data::*,
+    };
+
+    const SOURCE: &str = "192.0.2.1:32853";
+
+    fn request(header: Header, attr: Option<&dyn EncodeAttribute>) -> ([u8; 64], usize) {
+        let mut buf = [0; 64];
+        let mut builder = MessageBuilder::from_header(header, buf.as_mut_slice()).unwrap();
+        if let Some(attr) = attr {
+            builder.add(attr).unwrap();
+        }
+        builder.add_fingerprint().unwrap();
+        let len = builder.finish().len();
+        (buf, len)
+    }
+
+    fn header(method: Method) -> Header {
+        Header::new(Class::Request, method, 0, TransactionId::new([7; 12]))
+    }
+
+    fn parse<'a>(
+        msg: &'a [u8],
+        v: &'a mut [MaybeUninit<RawAttribute>],
+        options: ParseOptions,
+    ) -> MessageParser<'a, 'a> {
+        let msg = assert_ok!(MessageParser::from_complete_message_with(msg, v, options), "bad msg");
+        assert_ok!(msg.verify_fingerprint(), "bad fingerprint").expect("fingerprint");
+        assert_eq!(msg.find::<parsed::Software>(Type::SOFTWARE).unwrap().unwrap().as_str(), "srv");
+        msg
+    }
+
+    #[test]
+    fn test_binding() {
+        let server =
+            BindingServer::new().with_software(Software::new("srv")).with_fingerprint(true);
+        let (mut out, mut v) = ([0; 128], [MaybeUninit::uninit(); 8]);
+        let source = SOURCE.parse().unwrap();
+        let (req, len) = request(header(Method::BINDING), None);
+        let res = assert_ok!(server.handle(&req[..len], source, &mut out), "bad request");
+        let msg = parse(res.expect("response"), &mut v, ParseOptions::new());
+        assert_eq!(msg.class(), Class::SuccessResponse);
+        assert_eq!(msg.transaction_id(), &TransactionId::new([7; 12]));
+        let addr = msg.find::<XorMappedAddress>(Type::XOR_MAPPED_ADDRESS).unwrap().unwrap();
+        assert_eq!(addr.addr(), &source);
+
+        let header = Header::new_rfc3489(Class::Request, Method::BINDING, 0, *b"classic-rfc-3489");
+        let (classic, len) = request(header, None);
+        for (server, response) in [(&server, false), (&server.clone().with_rfc3489(true), true)] {
+            let res = assert_ok!(server.handle(&classic[..len], source, &mut out), "bad request");
+            assert_eq!(res.is_some(), response);
+        }
+        let server = server.with_rfc3489(true);
+        let res = server.handle(&classic[..len], source, &mut out).unwrap().unwrap();
+        let msg = parse(res, &mut v, ParseOptions::new().with_rfc3489(true));
+        assert_eq!(&msg.rfc3489_transaction_id(), b"classic-rfc-3489");
+        assert!(msg.find::<XorMappedAddress>(Type::XOR_MAPPED_ADDRESS).unwrap().is_none());
+        let addr = msg.find::<MappedAddress>(Type::MAPPED_ADDRESS).unwrap().unwrap();
+        assert_eq!(addr.addr(), &source);
+    }
+
+    #[test]
+    fn test_understood() {
+        fn class<U: UnderstoodAttributes>(
+            server: &BindingServer<&str, U>,
+            attr: &dyn EncodeAttribute,
+        ) -> Class {
+            let (mut out, mut v) = ([0; 128], [MaybeUninit::uninit(); 8]);
+            let (req, len) = request(header(Method::BINDING), Some(attr));
+            let res = server.handle(&req[..len], SOURCE.parse().unwrap(), &mut out);
+            parse(res.unwrap().unwrap(), &mut v, ParseOptions::new()).class()
+        }
+
+        let server =
+            BindingServer::new().with_software(Software::new("srv")).with_fingerprint(true);
+        // an ICE connectivity check
+        assert_eq!(class(&server, &Priority::new(0x6E00_1EFF)), Class::SuccessResponse);
+        assert_eq!(class(&server, &UseCandidate), Class::SuccessResponse);
+        // https://datatracker.ietf.org/doc/html/rfc5780#section-7.2, no alternate address to use
+        let change_request = ChangeRequest::new(true, true);
+        assert_eq!(class(&server, &change_request), Class::ErrorResponse);
+        let classic = server.clone().with_rfc3489(true);
+        assert!(classic.understands(Type::RESPONSE_ADDRESS));
+        assert_eq!(class(&classic, &change_request), Class::SuccessResponse);
+        let custom = server.with_understood([Type::CHANGE_REQUEST, Type::FINGERPRINT]);
+        assert_eq!(class(&custom, &change_request), Class::SuccessResponse);
+        assert_eq!(class(&custom, &UseCandidate), Class::ErrorResponse);
+    }
+
+    #[test]
+    fn test_discard() {
+        let (server, source) = (BindingServer::new(), SOURCE.parse().unwrap());
+        let mut out = [0; 128];
+        let (mut req, len) = request(header(Method::BINDING), None);
+        for len in [0, 19, len - 4] {
+            assert!(assert_ok!(server.handle(&req[..len], source, &mut out), "bad").is_none());
+        }
+        let res = assert_ok!(server.handle(&SAMPLE_IPV4_RESPONSE, source, &mut out), "bad");
+        assert!(res.is_none());
+        // corrupted fingerprint
+        req[len - 1] ^= 1;
+        assert!(assert_ok!(server.handle(&req[..len], source, &mut out), "bad").is_none());
+    }
+
+    #[test]
+    fn test_error_response() {
+        let server =
+            BindingServer::new().with_software(Software::new("srv")).with_fingerprint(true);
+        let (mut out, mut v) = ([0; 128], [MaybeUninit::uninit(); 8]);
+        let source = SOURCE.parse().unwrap();
+
+        let unknown = UnknownAttribute::new(Type::new(0x7FFF), &[]).unwrap();
+        let (req, len) = request(header(Method::BINDING), Some(&unknown));
+        let res = server.handle(&req[..len], source, &mut out).unwrap().unwrap();
+        let msg = parse(res, &mut v, ParseOptions::new());
+        assert_eq!(msg.class(), Class::ErrorResponse);
+        let code = msg.find::<parsed::ErrorCode>(Type::ERROR_CODE).unwrap().unwrap();
+        assert_eq!(code.code(), 420);
+        let unknown =
+            msg.find::<parsed::UnknownAttributes>(Type::UNKNOWN_ATTRIBUTES).unwrap().unwrap();
+        assert!(unknown.iter().eq([Type::new(0x7FFF)]));
+
+        for (method, attrs_len) in [(Method::ALLOCATE, None), (Method::BINDING, Some(8))] {
+            let (mut req, len) = request(header(method), None);
+            if let Some(attrs_len) = attrs_len {
+                // a truncated attribute behind a valid message length
+                req[20..24].copy_from_slice(&[0x80, 0x22, 0, attrs_len]);
+            }
+            let res = server.handle(&req[..len], source, &mut out).unwrap().unwrap();
+            let msg = parse(res, &mut v, ParseOptions::new());
+            let code = msg.find::<parsed::ErrorCode>(Type::ERROR_CODE).unwrap().unwrap();
+            assert_eq!(code.code(), 400);
+        }
+    }
+}
@@ -0,0 +1,5 @@
+mod binding;
+#[cfg(all(feature = "alloc", feature = "auth"))]
+pub mod turn;
+
+pub use binding::BindingServer;
@@ -0,0 +1,111 @@
+use crate::{
+    header::TransactionId,
+    net::{IpAddr, SocketAddr},
+    time::Instant,
+};
+use alloc::collections::BTreeMap;
+use core::time::Duration;
+
+// https://datatracker.ietf.org/doc/html/rfc8656#section-9
+pub(super) const PERMISSION_LIFETIME: Duration = Duration::from_secs(300);
+
+// https://datatracker.ietf.org/doc/html/rfc8656#section-12
+pub(super) const CHANNEL_LIFETIME: Duration = Duration::from_secs(600);
+
+// https://datatracker.ietf.org/doc/html/rfc8656#section-2.2
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Allocation<I> {
+    relayed: SocketAddr,
+    user: [u8; 32],
+    // The Allocate transaction and the lifetime it was granted, to answer its retransmissions
+    allocate: (TransactionId, u32),
+    expires: I,
+    permissions: BTreeMap<IpAddr, I>,
+    channels: BTreeMap<u16, (SocketAddr, I)>,
+}
+
+impl<I: Instant> Allocation<I> {
+    #[inline]
+    pub(super) fn new(
+        relayed: SocketAddr,
+        user: [u8; 32],
+        allocate: (TransactionId, u32),
+        expires: I,
+    ) -> Self {
+        let (permissions, channels) = (BTreeMap::new(), BTreeMap::new());
+        Self { relayed, user, allocate, expires, permissions, channels }
+    }
+
+    #[inline]
+    pub fn relayed(&self) -> SocketAddr {
+        self.relayed
+    }
+
+    #[inline]
+    pub fn expires(&self) -> I {
+        self.expires
+    }
+
+    #[inline]
+    pub fn has_permission(&self, ip: &IpAddr) -> bool {
+        self.permissions.contains_key(ip)
+    }
+
+    #[inline]
+    pub fn channel(&self, peer: &SocketAddr) -> Option<u16> {
+        self.channels.iter().find(|(_, (bound, _))| bound == peer).map(|(&channel, _)| channel)
+    }
+
+    #[inline]
+    pub fn peer(&self, channel: u16) -> Option<SocketAddr> {
+        self.channels.get(&channel).map(|&(peer, _)| peer)
+    }
+
+    // The SHA-256 USERHASH of the user that created the allocation
+    #[inline]
+    pub(super) fn user(&self) -> &[u8; 32] {
+        &self.user
+    }
+
+    #[inline]
+    pub(super) fn allocate(&self) -> (TransactionId, u32) {
+        self.allocate
+    }
+
+    #[inline]
+    pub(super) fn refresh(&mut self, expires: I) {
+        self.expires = expires;
+    }
+
+    #[inline]
+    pub(super) fn install_permission(&mut self, ip: IpAddr, now: I) {
+        self.permissions.insert(ip, now + PERMISSION_LIFETIME);
+    }
+
+    // A bound channel can't move to a different peer, nor a bound peer to a different channel.
+    // Binding also installs or refreshes the permission for the peer.
+    pub(super) fn bind_channel(&mut self, channel: u16, peer: SocketAddr, now: I) -> bool {
+        let rebound = self.peer(channel).is_some_and(|bound| bound != peer)
+            || self.channel(&peer).is_some_and(|bound| bound != channel);
+        if rebound {
+            return false;
+        }
+        self.channels.insert(channel, (peer, now + CHANNEL_LIFETIME));
+        self.install_permission(peer.ip(), now);
+        true
+    }
+
+    #[inline]
+    pub(super) fn poll_timeout(&self) -> I {
+        let permissions = self.permissions.values().copied();
+        let channels = self.channels.values().map(|&(_, expires)| expires);
+        permissions.chain(channels).fold(self.expires, Ord::min)
+    }
+
+    // Drops the expired permissions and channels, returning whether the allocation itself expired
+    pub(super) fn handle_timeout(&mut self, now: I) -> bool {
+        self.permissions.retain(|_, expires| *expires > now);
+        self.channels.retain(|_, (_, expires)| *expires > now);
+        self.expires <= now
+    }
+}
@@ -0,0 +1,897 @@
+use crate::{
+    attribute::{
+        addr::AddressFamily,
+        rfc8489::{ErrorCode, UnknownAttributes, XorMappedAddress},
+        rfc8656::{
+            self, ChannelNumber, Data, DontFragment, Lifetime, RequestedAddressFamily,
+            RequestedTransport, XorPeerAddress, XorRelayedAddress,
+        },
+        DecodeAttribute, Type,
+    },
+    auth::{Authenticated, CredentialStore, Rejection, ServerAuthenticator},
+    build::{Buffer, MessageBuilder},
+    channel_data::{ChannelData, Framing},
+    error::StunError,
+    header::{classify, Class, Header, Method, Packet, TransactionId},
+    net::SocketAddr,
+    parse::{AttributeOrdering, MessageParser, ParseOptions, RawAttribute},
+    server::BindingServer,
+    time::Instant,
+    util,
+};
+use alloc::{collections::BTreeMap, vec::Vec};
+use core::{mem::MaybeUninit, time::Duration};
+use sha2::{Digest, Sha256};
+
+mod allocation;
+
+pub use allocation::Allocation;
+
+const MAX_ATTRIBUTES: usize = 32;
+const MAX_UNKNOWN_ATTRIBUTES: usize = 16;
+
+// https://datatracker.ietf.org/doc/html/rfc8656#section-18
+const UNDERSTOOD: [Type; 16] = [
+    Type::USERNAME,
+    Type::USERHASH,
+    Type::REALM,
+    Type::NONCE,
+    Type::PASSWORD_ALGORITHM,
+    Type::PASSWORD_ALGORITHMS,
+    Type::MESSAGE_INTEGRITY,
+    Type::MESSAGE_INTEGRITY_SHA256,
+    Type::LIFETIME,
+    Type::REQUESTED_TRANSPORT,
+    Type::REQUESTED_ADDRESS_FAMILY,
+    Type::DONT_FRAGMENT,
+    Type::XOR_PEER_ADDRESS,
+    Type::CHANNEL_NUMBER,
+    Type::DATA,
+    Type::FINGERPRINT,
+];
+
+// https://datatracker.ietf.org/doc/html/rfc8656#section-2
+// The protocol is the IANA protocol number of the client to server transport, either
+// `RequestedTransport::UDP` or `RequestedTransport::TCP`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct FiveTuple {
+    pub client: SocketAddr,
+    pub server: SocketAddr,
+    pub protocol: u8,
+}
+
+impl FiveTuple {
+    #[inline]
+    pub fn framing(&self) -> Framing {
+        match self.protocol {
+            RequestedTransport::TCP => Framing::Stream,
+            _ => Framing::Datagram,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RelayError {
+    // Answered with 440 (Address Family not Supported)
+    UnsupportedFamily,
+    // Answered with 508 (Insufficient Capacity)
+    InsufficientCapacity,
+}
+
+// The relayed transport addresses, whose sockets are owned by the caller
+pub trait Relay {
+    fn allocate(
+        &mut self,
+        family: AddressFamily,
+        dont_fragment: bool,
+    ) -> Result<SocketAddr, RelayError>;
+
+    fn release(&mut self, relayed: SocketAddr);
+
+    fn send(&mut self, relayed: SocketAddr, peer: SocketAddr, data: &[u8]);
+}
+
+// https://datatracker.ietf.org/doc/html/rfc8656
+// Only UDP relaying is supported, EVEN-PORT and RESERVATION-TOKEN are answered with 420. Requests
+// are authenticated with long-term credentials from the store. Expired allocations, permissions
+// and channels are purged in `handle_timeout`. Binding requests are answered without
+// authentication.
+pub struct TurnServer<R, S, I> {
+    relay: R,
+    binding: BindingServer,
+    auth: ServerAuthenticator<S, I>,
+    secret: [u8; 32],
+    counter: u64,
+    max_lifetime: Duration,
+    user_quota: usize,
+    allocations: BTreeMap<FiveTuple, Allocation<I>>,
+    relayed: BTreeMap<SocketAddr, FiveTuple>,
+    quotas: BTreeMap<[u8; 32], usize>,
+}
+
+impl<R: Relay, S: CredentialStore, I: Instant> TurnServer<R, S, I> {
+    // https://datatracker.ietf.org/doc/html/rfc8656#section-7.2
+    pub const DEFAULT_LIFETIME: Duration = Duration::from_secs(600);
+
+    pub const MAX_LIFETIME: Duration = Duration::from_secs(3600);
+
+    pub const DEFAULT_USER_QUOTA: usize = 10;
+
+    // The secret keys the nonces and the DATA indication transaction ids, and should be random.
+    pub fn new(realm: &str, secret: [u8; 32], relay: R, store: S) -> Result<Self, StunError> {
+        Ok(Self {
+            relay,
+            binding: BindingServer::new(),
+            auth: ServerAuthenticator::new(realm, secret, store)?,
+            secret,
+            counter: 0,
+            max_lifetime: Self::MAX_LIFETIME,
+            user_quota: Self::DEFAULT_USER_QUOTA,
+            allocations: BTreeMap::new(),
+            relayed: BTreeMap::new(),
+            quotas: BTreeMap::new(),
+        })
+    }
+
+    // Can't be shorter than the default lifetime
+    #[inline]
+    pub fn with_max_lifetime(mut self, max_lifetime: Duration) -> Self {
+        self.max_lifetime = max_lifetime.max(Self::DEFAULT_LIFETIME);
+        self
+    }
+
+    #[inline]
+    pub fn with_nonce_lifetime(mut self, nonce_lifetime: Duration) -> Self {
+        self.auth = self.auth.with_nonce_lifetime(nonce_lifetime);
+        self
+    }
+
+    // The maximum number of allocations per user, before answering with 486 (Allocation Quota
+    // Reached)
+    #[inline]
+    pub fn with_user_quota(mut self, user_quota: usize) -> Self {
+        self.user_quota = user_quota;
+        self
+    }
+
+    #[inline]
+    pub fn relay(&self) -> &R {
+        &self.relay
+    }
+
+    #[inline]
+    pub fn relay_mut(&mut self) -> &mut R {
+        &mut self.relay
+    }
+
+    #[inline]
+    pub fn allocation(&self, tuple: &FiveTuple) -> Option<&Allocation<I>> {
+        self.allocations.get(tuple)
+    }
+
+    #[inline]
+    pub fn allocations(&self) -> usize {
+        self.allocations.len()
+    }
+
+    // Handles a STUN message or ChannelData message from a client, returning the response to send
+    // back on the 5-tuple, if any.
+    pub fn handle_client<'b, B: Buffer + ?Sized>(
+        &mut self,
+        tuple: FiveTuple,
+        src: &[u8],
+        now: I,
+        buf: &'b mut B,
+    ) -> Result<Option<&'b mut [u8]>, StunError> {
+        match classify(src) {
+            Packet::Stun(header) => self.handle_stun(tuple, header, src, now, buf),
+            Packet::ChannelData { .. } => {
+                self.handle_channel_data(tuple, src);
+                Ok(None)
+            }
+            _ => Ok(None),
+        }
+    }
+
+    // Handles data received from a peer on a relayed transport address, returning the ChannelData
+    // or DATA indication to send to the client on the 5-tuple, if the peer has a permission.
+    pub fn handle_peer<'b, B: Buffer + ?Sized>(
+        &mut self,
+        relayed: SocketAddr,
+        peer: SocketAddr,
+        data: &[u8],
+        buf: &'b mut B,
+    ) -> Result<Option<(FiveTuple, &'b mut [u8])>, StunError> {
+        let Some(tuple) = self.relayed.get(&relayed).copied() else {
+            return Ok(None);
+        };
+        let allocation = &self.allocations[&tuple];
+        if !allocation.has_permission(&peer.ip()) {
+            return Ok(None);
+        }
+        if let Some(channel) = allocation.channel(&peer) {
+            let (message, framing) = (ChannelData::new(channel, data)?, tuple.framing());
+            let len = message.encoded_len(framing);
+            message.encode(buf.reserve(0, len), framing)?;
+            return Ok(Some((tuple, buf.finish(len))));
+        }
+        let hash = self.random();
+        let transaction_id = TransactionId::new(*util::split_array_ref(&hash).0);
+        let mut builder =
+            MessageBuilder::new(Class::Indication, Method::DATA, transaction_id, buf)?;
+        builder.add(&XorPeerAddress::new(peer))?.add(&Data::new(data))?;
+        Ok(Some((tuple, builder.finish())))
+    }
+
+    #[inline]
+    pub fn poll_timeout(&self) -> Option<I> {
+        self.allocations.values().map(Allocation::poll_timeout).min()
+    }
+
+    pub fn handle_timeout(&mut self, now: I) {
+        let mut expired = Vec::new();
+        for (tuple, allocation) in &mut self.allocations {
+            if allocation.handle_timeout(now) {
+                expired.push(*tuple);
+            }
+        }
+        for tuple in expired {
+            self.remove(&tuple);
+        }
+    }
+
+    fn handle_stun<'b, B: Buffer + ?Sized>(
+        &mut self,
+        tuple: FiveTuple,
+        header: Header,
+        src: &[u8],
+        now: I,
+        buf: &'b mut B,
+    ) -> Result<Option<&'b mut [u8]>, StunError> {
+        if (header.class, header.method) == (Class::Request, Method::BINDING) {
+            return self.binding.handle(src, tuple.client, buf);
+        }
+        let mut attrs = [MaybeUninit::<RawAttribute>::uninit(); MAX_ATTRIBUTES];
+        let options = ParseOptions::new().with_ordering(AttributeOrdering::Trim);
+        let msg = match MessageParser::from_header_and_attrs_with(
+            header,
+            &src[Header::LEN..],
+            &mut attrs,
+            options,
+        ) {
+            Ok(msg) => msg,
+            Err(_) if header.class == Class::Request => {
+                let reply = Reply::Error(ErrorCode::BAD_REQUEST);
+                return self.respond(header, None, reply, now, buf).map(Some);
+            }
+            Err(_) => return Ok(None),
+        };
+        if msg.verify_fingerprint().is_err() {
+            return Ok(None);
+        }
+        match (msg.class(), msg.method()) {
+            (Class::Request, _) => self.handle_request(tuple, header, &msg, now, buf).map(Some),
+            (Class::Indication, Method::SEND) => {
+                self.handle_send(tuple, &msg);
+                Ok(None)
+            }
+            _ => Ok(None),
+        }
+    }
+
+    fn handle_request<'b, B: Buffer + ?Sized>(
+        &mut self,
+        tuple: FiveTuple,
+        header: Header,
+        msg: &MessageParser,
+        now: I,
+        buf: &'b mut B,
+    ) -> Result<&'b mut [u8], StunError> {
+        let credentials = match self.auth.authenticate(msg, now) {
+            Ok(credentials) => credentials,
+            Err(rejection) => {
+                return self.respond(header, None, Reply::Rejected(rejection), now, buf)
+            }
+        };
+        let mut unknown = [Type::new(0); MAX_UNKNOWN_ATTRIBUTES];
+        let reply = if let Some(unknown) = msg.check_comprehension(&UNDERSTOOD, &mut unknown) {
+            Reply::UnknownAttributes(unknown)
+        } else if self.allocations.get(&tuple).is_some_and(|a| a.user() != credentials.user()) {
+            Reply::Rejected(Rejection::WrongCredentials)
+        } else {
+            let user = *credentials.user();
+            match msg.method() {
+                Method::ALLOCATE => self.allocate(tuple, msg, user, now),
+                Method::REFRESH => self.refresh(tuple, msg, now),
+                Method::CREATE_PERMISSION => self.create_permission(tuple, msg, now),
+                Method::CHANNEL_BIND => self.channel_bind(tuple, msg, now),
+                _ => Err(ErrorCode::BAD_REQUEST),
+            }
+            .unwrap_or_else(Reply::Error)
+        };
+        self.respond(header, Some(&credentials), reply, now, buf)
+    }
+
+    // https://datatracker.ietf.org/doc/html/rfc8656#section-7.2
+    // A retransmission of the request that created the allocation is answered with the original
+    // success response.
+    fn allocate(
+        &mut self,
+        tuple: FiveTuple,
+        msg: &MessageParser,
+        user: [u8; 32],
+        now: I,
+    ) -> Result<Reply<'static>, ErrorCode> {
+        if let Some(allocation) = self.allocations.get(&tuple) {
+            return match allocation.allocate() {
+                (id, lifetime) if id == *msg.transaction_id() => {
+                    let (relayed, mapped) = (allocation.relayed(), tuple.client);
+                    Ok(Reply::Allocated { relayed, lifetime, mapped })
+                }
+                _ => Err(ErrorCode::ALLOCATION_MISMATCH),
+            };
+        }
+        let transport = find::<RequestedTransport>(msg, RequestedTransport::TYPE)?
+            .ok_or(ErrorCode::BAD_REQUEST)?;
+        if transport.protocol() != RequestedTransport::UDP {
+            return Err(ErrorCode::UNSUPPORTED_PROTOCOL);
+        }
+        let family = find::<RequestedAddressFamily>(msg, RequestedAddressFamily::TYPE)?
+            .map_or(AddressFamily::IPv4, |f| f.family());
+        let dont_fragment = find::<DontFragment>(msg, DontFragment::TYPE)?.is_some();
+        let lifetime =
+            find::<Lifetime>(msg, Lifetime::TYPE)?.map_or(Self::DEFAULT_LIFETIME, |lifetime| {
+                Duration::from_secs(lifetime.value().into())
+                    .clamp(Self::DEFAULT_LIFETIME, self.max_lifetime)
+            });
+        let quota = self.quotas.get(&user).copied().unwrap_or_default();
+        if quota >= self.user_quota {
+            return Err(ErrorCode::QUOTA_REACHED);
+        }
+        let relayed = self.relay.allocate(family, dont_fragment).map_err(|err| match err {
+            RelayError::UnsupportedFamily => ErrorCode::UNSUPPORTED_ADDRESS_FAMILY,
+            RelayError::InsufficientCapacity => ErrorCode::INSUFFICIENT_CAPACITY,
+        })?;
+        self.quotas.insert(user, quota + 1);
+        let (id, expires) = (*msg.transaction_id(), now + lifetime);
+        let lifetime = lifetime.as_secs() as u32;
+        self.allocations.insert(tuple, Allocation::new(relayed, user, (id, lifetime), expires));
+        self.relayed.insert(relayed, tuple);
+        Ok(Reply::Allocated { relayed, lifetime, mapped: tuple.client })
+    }
+
+    // https://datatracker.ietf.org/doc/html/rfc8656#section-8.2
+    fn refresh(
+        &mut self,
+        tuple: FiveTuple,
+        msg: &MessageParser,
+        now: I,
+    ) -> Result<Reply<'static>, ErrorCode> {
+        if !self.allocations.contains_key(&tuple) {
+            return Err(ErrorCode::ALLOCATION_MISMATCH);
+        }
+        let lifetime = match find::<Lifetime>(msg, Lifetime::TYPE)? {
+            Some(lifetime) if lifetime.value() == 0 => {
+                self.remove(&tuple);
+                return Ok(Reply::Lifetime(0));
+            }
+            Some(lifetime) => Duration::from_secs(lifetime.value().into())
+                .clamp(Self::DEFAULT_LIFETIME, self.max_lifetime),
+            None => Self::DEFAULT_LIFETIME,
+        };
+        if let Some(allocation) = self.allocations.get_mut(&tuple) {
+            allocation.refresh(now + lifetime);
+        }
+        Ok(Reply::Lifetime(lifetime.as_secs() as u32))
+    }
+
+    // https://datatracker.ietf.org/doc/html/rfc8656#section-10.2
+    // The permissions are only installed if all the XOR-PEER-ADDRESS attributes are valid.
+    fn create_permission(
+        &mut self,
+        tuple: FiveTuple,
+        msg: &MessageParser,
+        now: I,
+    ) -> Result<Reply<'static>, ErrorCode> {
+        let allocation = self.allocations.get_mut(&tuple).ok_or(ErrorCode::ALLOCATION_MISMATCH)?;
+        let peers = || {
+            let items = msg.iter_raw().filter(|item| item.attr() == XorPeerAddress::TYPE);
+            items
+                .map(|item| XorPeerAddress::decode(item.attr(), item.value(), msg.transaction_id()))
+        };
+        let mut count = 0;
+        for peer in peers() {
+            let peer = peer.map_err(|_| ErrorCode::BAD_REQUEST)?;
+            if peer.addr().is_ipv4() != allocation.relayed().is_ipv4() {
+                return Err(ErrorCode::ADDRESS_FAMILY_MISMATCH);
+            }
+            count += 1;
+        }
+        if count == 0 {
+            return Err(ErrorCode::BAD_REQUEST);
+        }
+        for peer in peers().flatten() {
+            allocation.install_permission(peer.addr().ip(), now);
+        }
+        Ok(Reply::Empty)
+    }
+
+    // https://datatracker.ietf.org/doc/html/rfc8656#section-11.2
+    fn channel_bind(
+        &mut self,
+        tuple: FiveTuple,
+        msg: &MessageParser,
+        now: I,
+    ) -> Result<Reply<'static>, ErrorCode> {
+        let allocation = self.allocations.get_mut(&tuple).ok_or(ErrorCode::ALLOCATION_MISMATCH)?;
+        let channel =
+            find::<ChannelNumber>(msg, ChannelNumber::TYPE)?.ok_or(ErrorCode::BAD_REQUEST)?;
+        let peer = *find::<XorPeerAddress>(msg, XorPeerAddress::TYPE)?
+            .ok_or(ErrorCode::BAD_REQUEST)?
+            .addr();
+        if peer.is_ipv4() != allocation.relayed().is_ipv4() {
+            return Err(ErrorCode::ADDRESS_FAMILY_MISMATCH);
+        }
+        if !allocation.bind_channel(channel.channel(), peer, now) {
+            return Err(ErrorCode::BAD_REQUEST);
+        }
+        Ok(Reply::Empty)
+    }
+
+    // https://datatracker.ietf.org/doc/html/rfc8656#section-10.3
+    fn handle_send(&mut self, tuple: FiveTuple, msg: &MessageParser) {
+        let Some(allocation) = self.allocations.get(&tuple) else {
+            return;
+        };
+        let peer = msg.find::<XorPeerAddress>(XorPeerAddress::TYPE);
+        let data = msg.find::<rfc8656::parsed::Data>(Type::DATA);
+        let (Ok(Some(peer)), Ok(Some(data))) = (peer, data) else {
+            return;
+        };
+        if allocation.has_permission(&peer.addr().ip()) {
+            self.relay.send(allocation.relayed(), *peer.addr(), data.data());
+        }
+    }
+
+    // https://datatracker.ietf.org/doc/html/rfc8656#section-12.6
+    fn handle_channel_data(&mut self, tuple: FiveTuple, src: &[u8]) {
+        let Ok(message) = ChannelData::decode(src, tuple.framing()) else {
+            return;
+        };
+        let Some(allocation) = self.allocations.get(&tuple) else {
+            return;
+        };
+        let Some(peer) = allocation.peer(message.channel()) else {
+            return;
+        };
+        if allocation.has_permission(&peer.ip()) {
+            self.relay.send(allocation.relayed(), peer, message.data());
+        }
+    }
+
+    fn respond<'b, B: Buffer + ?Sized>(
+        &mut self,
+        request: Header,
+        credentials: Option<&Authenticated>,
+        reply: Reply,
+        now: I,
+        buf: &'b mut B,
+    ) -> Result<&'b mut [u8], StunError> {
+        let class = match reply {
+            Reply::Allocated { .. } | Reply::Lifetime(_) | Reply::Empty => Class::SuccessResponse,
+            _ => Class::ErrorResponse,
+        };
+        let header = Header::new(class, request.method, 0, request.transaction_id);
+        let mut builder = MessageBuilder::from_header(header, buf)?;
+        match reply {
+            Reply::Allocated { relayed, lifetime, mapped } => {
+                builder.add(&XorRelayedAddress::new(relayed))?;
+                builder.add(&Lifetime::new(lifetime))?.add(&XorMappedAddress::new(mapped))?;
+            }
+            Reply::Lifetime(lifetime) => {
+                builder.add(&Lifetime::new(lifetime))?;
+            }
+            Reply::Empty => {}
+            Reply::Error(code) => {
+                builder.add(&code)?;
+            }
+            Reply::UnknownAttributes(unknown) => {
+                builder.add(&ErrorCode::UNKNOWN_ATTRIBUTE)?.add(&unknown)?;
+            }
+            Reply::Rejected(rejection) => {
+                self.auth.add_rejection(rejection, now, &mut builder)?;
+            }
+        }
+        if let Some(credentials) = credentials {
+            credentials.add_message_integrity(&mut builder)?;
+        }
+        Ok(builder.finish())
+    }
+
+    fn remove(&mut self, tuple: &FiveTuple) {
+        let Some(allocation) = self.allocations.remove(tuple) else {
+            return;
+        };
+        self.relayed.remove(&allocation.relayed());
+        self.relay.release(allocation.relayed());
+        if let Some(quota) = self.quotas.get_mut(allocation.user()) {
+            *quota -= 1;
+            if *quota == 0 {
+                self.quotas.remove(allocation.user());
+            }
+        }
+    }
+
+    #[inline]
+    fn random(&mut self) -> [u8; 32] {
+        self.counter += 1;
+        Sha256::new()
+            .chain_update(self.secret)
+            .chain_update(self.counter.to_be_bytes())
+            .finalize()
+            .into()
+    }
+}
+
+#[derive(Clone)]
+enum Reply<'a> {
+    Allocated { relayed: SocketAddr, lifetime: u32, mapped: SocketAddr },
+    Lifetime(u32),
+    Empty,
+    Error(ErrorCode),
+    UnknownAttributes(UnknownAttributes<&'a [Type]>),
+    // Answered by the authenticator, with a challenge for 401 and 438
+    Rejected(Rejection),
+}
+
+#[inline]
+fn find<'s, A: DecodeAttribute<'s>>(
+    msg: &MessageParser<'s, '_>,
+    attr: Type,
+) -> Result<Option<A>, ErrorCode> {
+    msg.find(attr).map_err(|_| ErrorCode::BAD_REQUEST)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        attribute::{
+            password::Algorithm,
+            rfc8489::{parsed, Nonce, Realm, Username},
+            rfc8656::EvenPort,
+            EncodeAttribute,
+        },
+        auth::{LongTermKey, MemoryStore},
+        net::{IpAddr, Ipv4Addr},
+        test_data::assert_ok,
+    };
+    use alloc::{string::String, vec};
+    use core::sync::atomic::{AtomicU8, Ordering};
+
+    const REALM: &str = "example.org";
+    const PASSWORD: &str = "password";
+    const USERS: [&str; 2] = ["user", "other"];
+    const RELAYED: Ipv4Addr = Ipv4Addr::new(203, 0, 113, 1);
+
+    type Server = TurnServer<TestRelay, MemoryStore, Duration>;
+
+    #[derive(Default)]
+    struct TestRelay {
+        capacity: usize,
+        ports: u16,
+        allocated: Vec<SocketAddr>,
+        sent: Vec<(SocketAddr, SocketAddr, Vec<u8>)>,
+    }
+
+    impl Relay for TestRelay {
+        fn allocate(&mut self, family: AddressFamily, _: bool) -> Result<SocketAddr, RelayError> {
+            if family != AddressFamily::IPv4 {
+                return Err(RelayError::UnsupportedFamily);
+            }
+            if self.allocated.len() >= self.capacity {
+                return Err(RelayError::InsufficientCapacity);
+            }
+            self.ports += 1;
+            let relayed = SocketAddr::new(RELAYED.into(), 49152 + self.ports);
+            self.allocated.push(relayed);
+            Ok(relayed)
+        }
+
+        fn release(&mut self, relayed: SocketAddr) {
+            self.allocated.retain(|allocated| *allocated != relayed);
+        }
+
+        fn send(&mut self, relayed: SocketAddr, peer: SocketAddr, data: &[u8]) {
+            self.sent.push((relayed, peer, data.to_vec()));
+        }
+    }
+
+    fn key(username: &str) -> LongTermKey {
+        let (username, realm) = (Username::new(username), Realm::new(REALM));
+        LongTermKey::new(Algorithm::MD5, &username, &realm, PASSWORD).unwrap()
+    }
+
+    fn server(capacity: usize) -> Server {
+        let relay = TestRelay { capacity, ..Default::default() };
+        let mut store = MemoryStore::new();
+        for user in USERS {
+            assert_ok!(store.insert(user, REALM, PASSWORD), "bad credentials");
+        }
+        assert_ok!(TurnServer::new(REALM, [7; 32], relay, store), "bad realm")
+    }
+
+    fn tuple(port: u16) -> FiveTuple {
+        let (client, server) = (Ipv4Addr::new(192, 0, 2, 1), Ipv4Addr::new(192, 0, 2, 100));
+        let (client, server) =
+            (SocketAddr::new(client.into(), port), SocketAddr::new(server.into(), 3478));
+        FiveTuple { client, server, protocol: RequestedTransport::UDP }
+    }
+
+    fn peer(ip: IpAddr) -> SocketAddr {
+        SocketAddr::new(ip, 5000)
+    }
+
+    // Sends a request authenticated by `user` with `nonce` in a new transaction, returning the
+    // response
+    fn exchange(
+        server: &mut Server,
+        tuple: FiveTuple,
+        method: Method,
+        user: Option<(&str, &str)>,
+        attrs: &[&dyn EncodeAttribute],
+        now: Duration,
+    ) -> Vec<u8> {
+        static TRANSACTIONS: AtomicU8 = AtomicU8::new(0);
+        let id = TransactionId::new([TRANSACTIONS.fetch_add(1, Ordering::Relaxed); 12]);
+        transact(server, tuple, id, method, user, attrs, now)
+    }
+
+    fn transact(
+        server: &mut Server,
+        tuple: FiveTuple,
+        id: TransactionId,
+        method: Method,
+        user: Option<(&str, &str)>,
+        attrs: &[&dyn EncodeAttribute],
+        now: Duration,
+    ) -> Vec<u8> {
+        let mut req = [0; 256];
+        let mut builder = MessageBuilder::new(Class::Request, method, id, &mut req).unwrap();
+        for attr in attrs {
+            builder.add(*attr).unwrap();
+        }
+        if let Some((username, nonce)) = user {
+            builder.add(&Username::new(username)).unwrap().add(&Realm::new(REALM)).unwrap();
+            builder.add(&Nonce::new(nonce)).unwrap();
+            builder.add_message_integrity(key(username).as_ref()).unwrap();
+        }
+        let req = builder.finish();
+        let mut out = Vec::new();
+        let res = assert_ok!(server.handle_client(tuple, req, now, &mut out), "bad request");
+        let res = res.expect("response").to_vec();
+        if let Some((username, _)) =
+            user.filter(|_| error_code(&res).is_none_or(|c| c != 401 && c != 438))
+        {
+            let mut v = [MaybeUninit::uninit(); 16];
+            let msg = MessageParser::from_complete_message(&res, &mut v).unwrap();
+            let integrity =
+                assert_ok!(msg.verify_message_integrity(key(username).as_ref()), "bad mi");
+            assert!(integrity.is_some());
+        }
+        res
+    }
+
+    fn error_code(res: &[u8]) -> Option<u16> {
+        let mut v = [MaybeUninit::uninit(); 16];
+        let msg = MessageParser::from_complete_message(res, &mut v).unwrap();
+        msg.find::<parsed::ErrorCode>(Type::ERROR_CODE).unwrap().map(|code| code.code())
+    }
+
+    fn nonce(server: &mut Server, tuple: FiveTuple) -> String {
+        let res = exchange(server, tuple, Method::ALLOCATE, None, &[], Duration::ZERO);
+        assert_eq!(error_code(&res), Some(401));
+        let mut v = [MaybeUninit::uninit(); 16];
+        let msg = MessageParser::from_complete_message(&res, &mut v).unwrap();
+        let realm = msg.find::<parsed::Realm>(Type::REALM).unwrap().expect("realm");
+        assert_eq!(realm.as_str(), REALM);
+        msg.find::<parsed::Nonce>(Type::NONCE).unwrap().expect("nonce").as_str().into()
+    }
+
+    fn allocate(server: &mut Server, tuple: FiveTuple, user: &str, nonce: &str) -> Vec<u8> {
+        let transport = RequestedTransport::new(RequestedTransport::UDP);
+        exchange(
+            server,
+            tuple,
+            Method::ALLOCATE,
+            Some((user, nonce)),
+            &[&transport],
+            Duration::ZERO,
+        )
+    }
+
+    #[test]
+    fn test_allocate() {
+        let mut server = server(1).with_user_quota(1);
+        let nonce = nonce(&mut server, tuple(1));
+        let user = Some((USERS[0], nonce.as_str()));
+        let res = allocate(&mut server, tuple(1), USERS[0], "stale");
+        assert_eq!(error_code(&res), Some(438));
+
+        let lifetime = Lifetime::new(60);
+        let transport = RequestedTransport::new(RequestedTransport::UDP);
+        let res = exchange(
+            &mut server,
+            tuple(1),
+            Method::ALLOCATE,
+            user,
+            &[&transport, &lifetime, &DontFragment],
+            Duration::ZERO,
+        );
+        let mut v = [MaybeUninit::uninit(); 16];
+        let msg = MessageParser::from_complete_message(&res, &mut v).unwrap();
+        assert_eq!(msg.class(), Class::SuccessResponse);
+        let relayed = msg.find::<XorRelayedAddress>(XorRelayedAddress::TYPE).unwrap().unwrap();
+        assert_eq!(relayed.addr(), &SocketAddr::new(RELAYED.into(), 49153));
+        let lifetime = msg.find::<Lifetime>(Lifetime::TYPE).unwrap().unwrap();
+        assert_eq!(lifetime.value(), 600);
+        let mapped = msg.find::<XorMappedAddress>(XorMappedAddress::TYPE).unwrap().unwrap();
+        assert_eq!(mapped.addr(), &tuple(1).client);
+        assert_eq!(server.allocation(&tuple(1)).unwrap().expires(), Duration::from_secs(600));
+        // a retransmission gets the same response, a new transaction an allocation mismatch
+        let id = *msg.transaction_id();
+        let attrs = [&transport as &dyn EncodeAttribute, &lifetime, &DontFragment];
+        let now = Duration::from_secs(1);
+        let retransmitted =
+            transact(&mut server, tuple(1), id, Method::ALLOCATE, user, &attrs, now);
+        assert_eq!(retransmitted, res);
+        assert_eq!(server.allocations(), 1);
+        // a refresh is held to the same minimum lifetime
+        let short = Lifetime::new(60);
+        let res = exchange(&mut server, tuple(1), Method::REFRESH, user, &[&short], now);
+        let msg = MessageParser::from_complete_message(&res, &mut v).unwrap();
+        assert_eq!(msg.class(), Class::SuccessResponse);
+        let refreshed = msg.find::<Lifetime>(Lifetime::TYPE).unwrap().unwrap();
+        assert_eq!(refreshed.value(), 600);
+        assert_eq!(server.allocation(&tuple(1)).unwrap().expires(), Duration::from_secs(601));
+
+        let even_port = EvenPort::new(false);
+        for (tuple, user, attrs, code) in [
+            (tuple(1), USERS[0], &[&transport as &dyn EncodeAttribute][..], 437),
+            (tuple(2), USERS[0], &[&transport], 486),
+            (tuple(2), USERS[1], &[&transport], 508),
+            (tuple(2), USERS[1], &[&RequestedTransport::new(RequestedTransport::TCP)], 442),
+            (
+                tuple(2),
+                USERS[1],
+                &[&transport, &RequestedAddressFamily::new(AddressFamily::IPv6)],
+                440,
+            ),
+            (tuple(2), USERS[1], &[], 400),
+            (tuple(2), USERS[1], &[&transport, &even_port], 420),
+            (tuple(1), USERS[1], &[], 441),
+        ] {
+            let method = if code == 441 { Method::REFRESH } else { Method::ALLOCATE };
+            let res =
+                exchange(&mut server, tuple, method, Some((user, &nonce)), attrs, Duration::ZERO);
+            assert_eq!(error_code(&res), Some(code));
+        }
+        let res = exchange(
+            &mut server,
+            tuple(2),
+            Method::REFRESH,
+            Some((USERS[1], &nonce)),
+            &[],
+            Duration::ZERO,
+        );
+        assert_eq!(error_code(&res), Some(437));
+
+        let res = exchange(
+            &mut server,
+            tuple(1),
+            Method::REFRESH,
+            user,
+            &[&Lifetime::new(0)],
+            Duration::ZERO,
+        );
+        assert_eq!(error_code(&res), None);
+        assert_eq!(server.allocations(), 0);
+        assert!(server.relay().allocated.is_empty());
+        assert_eq!(error_code(&allocate(&mut server, tuple(2), USERS[1], &nonce)), None);
+        assert_eq!(server.poll_timeout(), Some(Duration::from_secs(600)));
+        server.handle_timeout(Duration::from_secs(600));
+        assert_eq!(server.allocations(), 0);
+        assert!(server.relay().allocated.is_empty());
+        // the quota was released along with the allocation
+        let res = allocate(&mut server, tuple(2), USERS[1], &nonce);
+        assert_eq!(error_code(&res), None);
+    }
+
+    #[test]
+    fn test_binding() {
+        let mut server = server(1);
+        let res = exchange(&mut server, tuple(1), Method::BINDING, None, &[], Duration::ZERO);
+        let mut v = [MaybeUninit::uninit(); 16];
+        let msg = MessageParser::from_complete_message(&res, &mut v).unwrap();
+        assert_eq!((msg.class(), msg.method()), (Class::SuccessResponse, Method::BINDING));
+        let mapped = msg.find::<XorMappedAddress>(XorMappedAddress::TYPE).unwrap().unwrap();
+        assert_eq!(mapped.addr(), &tuple(1).client);
+        assert_eq!(server.allocations(), 0);
+    }
+
+    #[test]
+    fn test_relay() {
+        let mut server = server(1);
+        let nonce = nonce(&mut server, tuple(1));
+        let (user, now) = (Some((USERS[0], nonce.as_str())), Duration::ZERO);
+        allocate(&mut server, tuple(1), USERS[0], &nonce);
+        let relayed = server.allocation(&tuple(1)).unwrap().relayed();
+        let (peer, other) = (
+            peer(Ipv4Addr::new(198, 51, 100, 1).into()),
+            peer(Ipv4Addr::new(198, 51, 100, 2).into()),
+        );
+        let mut out = vec![];
+        assert!(
+            assert_ok!(server.handle_peer(relayed, peer, b"hi", &mut out), "bad data").is_none()
+        );
+
+        let mut send = [0; 64];
+        let id = TransactionId::new([1; 12]);
+        let mut builder =
+            MessageBuilder::new(Class::Indication, Method::SEND, id, &mut send).unwrap();
+        builder.add(&XorPeerAddress::new(peer)).unwrap().add(&Data::new(b"hello")).unwrap();
+        let send = builder.finish();
+        assert!(
+            assert_ok!(server.handle_client(tuple(1), send, now, &mut out), "bad send").is_none()
+        );
+        assert!(server.relay().sent.is_empty());
+
+        let v6 = XorPeerAddress::new(self::peer(IpAddr::V6(Ipv4Addr::LOCALHOST.to_ipv6_mapped())));
+        for (attrs, code) in [
+            (&[][..], Some(400)),
+            (&[&v6 as &dyn EncodeAttribute], Some(443)),
+            (&[&XorPeerAddress::new(peer)], None),
+        ] {
+            let res = exchange(&mut server, tuple(1), Method::CREATE_PERMISSION, user, attrs, now);
+            assert_eq!(error_code(&res), code);
+        }
+        assert!(
+            assert_ok!(server.handle_client(tuple(1), send, now, &mut out), "bad send").is_none()
+        );
+        assert_eq!(server.relay().sent, [(relayed, peer, b"hello".to_vec())]);
+
+        let (client, data) = server.handle_peer(relayed, peer, b"hi", &mut out).unwrap().unwrap();
+        assert_eq!(client, tuple(1));
+        let mut v = [MaybeUninit::uninit(); 16];
+        let msg = MessageParser::from_complete_message(data, &mut v).unwrap();
+        assert_eq!((msg.class(), msg.method()), (Class::Indication, Method::DATA));
+        assert_eq!(
+            msg.find::<XorPeerAddress>(XorPeerAddress::TYPE).unwrap().unwrap().addr(),
+            &peer
+        );
+        let data = msg.find::<rfc8656::parsed::Data>(Type::DATA).unwrap().unwrap();
+        assert_eq!(data.data(), b"hi");
+        assert!(server.handle_peer(relayed, other, b"hi", &mut out).unwrap().is_none());
+
+        let channel = ChannelNumber::new(0x4000).unwrap();
+        for (channel, peer, code) in [
+            (channel, peer, None),
+            (ChannelNumber::new(0x4001).unwrap(), peer, Some(400)),
+            (channel, other, Some(400)),
+        ] {
+            let attrs = [&channel as &dyn EncodeAttribute, &XorPeerAddress::new(peer)];
+            let res = exchange(&mut server, tuple(1), Method::CHANNEL_BIND, user, &attrs, now);
+            assert_eq!(error_code(&res), code);
+        }
+        let (_, data) = server.handle_peer(relayed, peer, b"hi", &mut out).unwrap().unwrap();
+        assert_eq!(data, b"\x40\x00\x00\x02hi");
+        let res = server.handle_client(tuple(1), b"\x40\x00\x00\x02yo", now, &mut out);
+        assert!(assert_ok!(res, "bad channel data").is_none());
+        assert_eq!(server.relay().sent[1], (relayed, peer, b"yo".to_vec()));
+
+        assert_eq!(server.poll_timeout(), Some(Duration::from_secs(300)));
+        server.handle_timeout(Duration::from_secs(300));
+        assert!(server.handle_peer(relayed, peer, b"hi", &mut out).unwrap().is_none());
+        assert_eq!(server.allocations(), 1);
+    }
+}
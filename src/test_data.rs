@@ -134,6 +134,11 @@ pub static SAMPLE_REQUEST_LONG_TERM_AUTH: [u8; 116] = [
     0x8c, 0xa8, 0x96, 0x66, // }
 ];
 
+#[cfg(feature = "auth")]
+pub const LONG_TERM_USERNAME: &str = "\u{30DE}\u{30C8}\u{30EA}\u{30C3}\u{30AF}\u{30B9}";
+#[cfg(feature = "auth")]
+pub const LONG_TERM_REALM: &str = "example.org";
+
 // [`RFC8489`](https://datatracker.ietf.org/doc/html/rfc8489)
 
 /// B.1.  Sample Request with Long-Term Authentication with
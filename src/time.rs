@@ -0,0 +1,6 @@
+use core::{ops::Add, time::Duration};
+
+// A caller provided clock, `std::time::Instant` or anything else monotonic works.
+pub trait Instant: Copy + Ord + Add<Duration, Output = Self> {}
+
+impl<T: Copy + Ord + Add<Duration, Output = T>> Instant for T {}